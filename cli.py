@@ -13,8 +13,7 @@ from matplotlib import rcParams
 from ase.io import read as aseread
 
 from classes import from_file as from_file_classes
-from basis_vectors import full_lattice_from_basis_vectors
-from crystacean import Lattice, from_dft_json  # type: ignore
+from crystacean import from_basis_vectors, from_dft_json  # type: ignore
 from cull_results import cull
 
 # Test libraries required for other files.
@@ -56,6 +55,17 @@ def path_empty_callback(path: str):
     return path
 
 
+def write_solution_ids(save_to: str, lattice, files_and_solutions):
+    """
+    Write the stable identifier of every saved solution next to its file name,
+    so results can be matched up across reruns.
+    """
+    with open(f"{save_to}/solution_ids.csv", "w") as ids_file:
+        ids_file.write("file,id\n")
+        for file_name, solution in files_and_solutions:
+            ids_file.write(f"{file_name},{lattice.solution_id(solution)}\n")
+
+
 HELP_CREATE = "Creation"
 HELP_INPUT = "Input"
 HELP_OUTPUT = "Output"
@@ -207,6 +217,13 @@ def from_size(
             rich_help_panel=HELP_CREATE,
         ),
     ] = 1,
+    size_y: Annotated[
+        Optional[int],
+        typer.Option(
+            help="Size of the base lattice in the y direction. Defaults to --size.",
+            rich_help_panel=HELP_CREATE,
+        ),
+    ] = None,
     plot: PLOT_BOOL = False,
     save_to: SAVETO_STR = "",
     max_singlets: SINGLET_INT = 2,
@@ -214,14 +231,13 @@ def from_size(
     similarity_filter: SIMILARIRY_OPT_FLOAT = None,
 ):
     """
-    Create SiO2 interface structures for a SiC unit cell with 4*size attachment points.
+    Create SiO2 interface structures for a SiC unit cell with 4*size*size_y attachment points.
     """
     if not (plot or save_to):
         print("NOTE: both plot and save_to are false!")
     if save_to:
         os.mkdir(save_to)
-    custom_boundary_points = full_lattice_from_basis_vectors(size)
-    lattice = Lattice(custom_boundary_points)
+    lattice = from_basis_vectors(size, size_y if size_y else size)
 
     if plot:
         plt.plot(
@@ -261,7 +277,7 @@ def from_size(
     print(bit_lattice)
 
     if use_parallel:
-        solution = bit_lattice.solve_parallel(True)
+        solutions = bit_lattice.solve_parallel(True)
     else:
         solutions = bit_lattice.solve(True)
 
@@ -270,6 +286,15 @@ def from_size(
         total=len(solutions),
         bar_format="{l_bar}{bar}| {n_fmt}/{total_fmt}  ",
     )
+    if save_to:
+        write_solution_ids(
+            save_to,
+            lattice,
+            (
+                (f"{name}_{number:>06}.json", solution)
+                for number, solution in enumerate(solutions)
+            ),
+        )
     for number, solution in progress:
         solved_lattice = lattice.to_solved_lattice(solution)
         if save_to:
@@ -706,6 +731,14 @@ def ase_json_handler(
                 )
             else:
                 solved_lattice.export(save_to, f"{prefix}_{number:>04}.json")
+        write_solution_ids(
+            save_to,
+            lattice,
+            (
+                (f"{prefix}_{number:>04}.json", solution)
+                for number, solution in enumerate(solutions)
+            ),
+        )
 
     if statistics:
         solutions_dict = Counter()
@@ -0,0 +1,78 @@
+//! Compares recomputing the available sites of every structure with
+//! `matrix_vector_multiply` to keeping them up to date with `available_after`,
+//! on the same depth-first walk through the first `NODES` structures.
+use crystacean_rs::{bit_array_settings, BitArrayFilter, BitArrayRepresentation, Lattice};
+use fixedbitset::FixedBitSet;
+
+const NODES: usize = 100_000;
+
+fn intermediary(structure: &str) -> BitArrayRepresentation {
+    let lattice =
+        Lattice::from_dft_json(format!("../test_lattices/{}", structure), 1.1, true).unwrap();
+
+    let options = bit_array_settings!(
+        lattice,
+        solve_filter = BitArrayFilter::None,
+        max_singlets = 0
+    );
+
+    lattice.get_intermediary(options)
+}
+
+#[divan::bench(max_time = 60, args = ["T16.json", "T20.json"])]
+fn full_multiply(bencher: divan::Bencher, structure: &str) {
+    let bit_lattice = intermediary(structure);
+    bencher.bench_local(|| {
+        let mut stack = vec![bit_lattice.get_bitarray()];
+        let mut explored = 0;
+        while let Some(candidate) = stack.pop() {
+            explored += 1;
+            if explored == NODES {
+                break;
+            }
+            let Ok(possibilities) = bit_lattice.get_possibilities(&candidate) else {
+                continue;
+            };
+            for fillable_site in possibilities.ones().rev() {
+                let mut new_candidate = candidate.clone();
+                new_candidate.insert(fillable_site);
+                stack.push(new_candidate);
+            }
+        }
+        explored
+    });
+}
+
+#[divan::bench(max_time = 60, args = ["T16.json", "T20.json"])]
+fn incremental(bencher: divan::Bencher, structure: &str) {
+    let bit_lattice = intermediary(structure);
+    bencher.bench_local(|| {
+        let filled_sites = bit_lattice.get_bitarray();
+        let available = bit_lattice.matrix_vector_multiply(&filled_sites);
+        let mut stack: Vec<(FixedBitSet, FixedBitSet)> = vec![(filled_sites, available)];
+        let mut explored = 0;
+        while let Some((candidate, available)) = stack.pop() {
+            explored += 1;
+            if explored == NODES {
+                break;
+            }
+            let Ok(possibilities) = bit_lattice.possibilities_from(&candidate, available.clone())
+            else {
+                continue;
+            };
+            for fillable_site in possibilities.ones().rev() {
+                let mut new_candidate = candidate.clone();
+                new_candidate.insert(fillable_site);
+                stack.push((
+                    new_candidate,
+                    bit_lattice.available_after(&available, fillable_site),
+                ));
+            }
+        }
+        explored
+    });
+}
+
+fn main() {
+    divan::main();
+}
@@ -9,7 +9,8 @@ fn similarity(margin: f32) -> Vec<BitArraySolution> {
         "../test_lattices/T20.json".into(),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -36,7 +37,8 @@ fn none() -> Vec<BitArraySolution> {
         "../test_lattices/T20.json".into(),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -1,8 +1,4 @@
-use crystacean_rs::{
-    bit_array_settings,
-    test_points::{huge_points, lattice_points},
-    BitArrayFilter, BitArraySettings, BitArraySolution, Lattice,
-};
+use crystacean_rs::{bit_array_settings, BitArrayFilter, BitArraySolution, Lattice};
 use termion::{clear, cursor};
 
 // #[divan::bench(max_time = 120, args = [true, false])]
@@ -27,7 +23,8 @@ fn huge_run(filter: BitArrayFilter) -> Vec<BitArraySolution> {
         "../test_lattices/T16.json".into(),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -55,7 +52,8 @@ fn scaling(structure: &str) -> Vec<BitArraySolution> {
         format!("../test_lattices/{}", structure),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -82,7 +80,8 @@ fn simil_0_1(structure: &str) -> Vec<BitArraySolution> {
         format!("../test_lattices/{}", structure),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -109,7 +108,8 @@ fn simil_0_5(structure: &str) -> Vec<BitArraySolution> {
         format!("../test_lattices/{}", structure),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
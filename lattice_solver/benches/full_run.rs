@@ -3,7 +3,7 @@ use crystacean_rs::{bit_array_settings, test_points::lattice_points, BitArraySet
 use std::time::Duration;
 
 fn criterion_long(c: &mut Criterion) {
-    let lattice = Lattice::python_new(lattice_points(), 1.1, true);
+    let lattice = Lattice::python_new(lattice_points(), 1.1, true).unwrap();
     let options = bit_array_settings!(&lattice);
     let bit_lattice = lattice.get_intermediary(options);
     let mut test_bitarray = bit_lattice.get_bitarray();
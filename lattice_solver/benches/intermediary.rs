@@ -0,0 +1,22 @@
+//! Time and memory of building the intermediary of growing silicon carbide
+//! supercells, which should grow with the amount of neighbouring sites rather
+//! than with the square of the amount of sites.
+use crystacean_rs::{
+    basis_vectors::{SIC_A, SIC_B},
+    BitArraySettings, Lattice,
+};
+use divan::AllocProfiler;
+
+#[global_allocator]
+static ALLOC: AllocProfiler = AllocProfiler::system();
+
+#[divan::bench(args = [2, 3, 4, 5, 6])]
+fn get_intermediary(bencher: divan::Bencher, size: usize) {
+    let lattice = Lattice::from_basis_vectors(size, size, SIC_A, SIC_B).unwrap();
+    let options = BitArraySettings::default(&lattice);
+    bencher.bench_local(|| lattice.get_intermediary(options));
+}
+
+fn main() {
+    divan::main();
+}
@@ -1,9 +1,5 @@
-use crystacean_rs::{
-    bit_array_settings,
-    test_points::{huge_points, lattice_points},
-    BitArrayFilter, BitArraySettings, BitArraySolution, Lattice,
-};
-use termion::{cursor, clear};
+use crystacean_rs::{bit_array_settings, BitArrayFilter, BitArraySolution, Lattice};
+use termion::clear;
 
 fn main() {
     for margin in [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0].into_iter().rev() {
@@ -21,7 +17,8 @@ fn similarity(margin: f32) -> Vec<BitArraySolution> {
         "../test_lattices/T20.json".into(),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
@@ -40,7 +37,8 @@ fn none() -> Vec<BitArraySolution> {
         "../test_lattices/T20.json".into(),
         1.1,
         true,
-    );
+    )
+    .unwrap();
 
     let options = bit_array_settings!(
         lattice,
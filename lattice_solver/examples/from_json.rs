@@ -1,9 +1,11 @@
 use std::process::exit;
 
-use crystacean_rs::{bit_array_settings, BitArrayFilter, BitArraySettings, Lattice};
+use crystacean_rs::{bit_array_settings, BitArrayFilter, Lattice};
 
+// Stops after printing the distances; the solve below is kept for later use.
+#[allow(unreachable_code)]
 fn main() {
-    let lattice = Lattice::from_dft_json("../test_lattices/T20.json".into(), 3.5, false);
+    let lattice = Lattice::from_dft_json("../test_lattices/T20.json".into(), 3.5, false).unwrap();
 
     let options = bit_array_settings!(
         lattice,
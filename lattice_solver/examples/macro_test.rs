@@ -1,7 +1,7 @@
 use crystacean_rs::{BitArraySettings, Lattice, test_points::lattice_points, bit_array_settings, BitArrayFilter};
 
 fn main() {
-    let lattice = Lattice::python_new(lattice_points(), 1.1, true);
+    let lattice = Lattice::python_new(lattice_points(), 1.1, true).unwrap();
     let filter = bit_array_settings!(
         lattice,
         max_singlets = 1
@@ -1,8 +1,8 @@
-use crystacean_rs::{bit_array_settings, BitArrayFilter, Lattice};
+use crystacean_rs::{bit_array_settings, BitArrayFilter, Lattice, LatticeError};
 
-fn main() {
+fn main() -> Result<(), LatticeError> {
     // Read a lattice from a file with good standard values.
-    let lattice = Lattice::from_dft_json("../test_lattices/T16.json".into(), 1.1, true);
+    let lattice = Lattice::from_dft_json("../test_lattices/T16.json".into(), 1.1, true)?;
 
     // The options for the bit array are given in a macro, to allow for default values
     let options = bit_array_settings!(
@@ -24,6 +24,7 @@ fn main() {
         let solved_lattice = lattice.to_solved_lattice(solution);
         solved_lattice.export_as_ase_json(
             &format!("../exports/T16_example/example_{number:>4}.json")
-        );
+        )?;
     }
-}
\ No newline at end of file
+    Ok(())
+}
@@ -1,9 +1,9 @@
 use crystacean_rs::{
-    bit_array_settings, test_points::{huge_points, lattice_points}, BitArrayFilter, BitArraySettings, Lattice,
+    bit_array_settings, test_points::huge_points, BitArrayFilter, Lattice,
 };
 
 fn main() {
-    let lattice = Lattice::python_new(huge_points(), 1.1, true);
+    let lattice = Lattice::python_new(huge_points(), 1.1, true).unwrap();
     let tolerance = 0.9f32;
 
     let trees_options = bit_array_settings!(
@@ -0,0 +1,119 @@
+//! Read-only views of the points and candidate sites of a `Lattice`.
+use std::{collections::HashMap, sync::Arc};
+
+use crate::*;
+
+/// An attachment point of a `Lattice`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentPoint {
+    pub index: LatticeIndex,
+    pub position: [f32; 3],
+    /// Unit vector from the substrate atom to this point.
+    pub bond: [f32; 3],
+    /// The point this point is a periodic image of, if it is a ghost.
+    pub ghost_of: Option<LatticeIndex>,
+    /// The candidate sites connected to this point. Ghosts share the sites of
+    /// their parent.
+    pub sites: Vec<OxygenIndex>,
+}
+
+/// A candidate site of a `Lattice`, at which a new atom can be placed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandidateSite<'a> {
+    pub index: OxygenIndex,
+    pub kind: SiteKind,
+    pub position: [f32; 3],
+    /// The attachment points bridged by this site, which can be ghosts.
+    pub points: &'a [LatticeIndex],
+    /// The sites which can not be occupied together with this site.
+    pub exclusions: &'a [OxygenIndex],
+}
+
+impl Lattice {
+    /// For every point, the index of the point it is a ghost of.
+    pub(crate) fn ghost_parents(&self) -> Vec<Option<LatticeIndex>> {
+        let indices: HashMap<*const LatticePoint, usize> = self
+            .points
+            .iter()
+            .enumerate()
+            .map(|(number, point)| (Arc::as_ptr(point), number))
+            .collect();
+        self.points
+            .iter()
+            .map(|point| {
+                point
+                    .ghost_to
+                    .as_ref()
+                    .and_then(|parent| indices.get(&Arc::as_ptr(parent)))
+                    .map(|&number| LatticeIndex(number))
+            })
+            .collect()
+    }
+
+    /// Iterate over the attachment points of the `Lattice`, including ghosts.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, Lattice};
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let points = lattice.attachment_points().collect::<Vec<_>>();
+    /// assert_eq!(points.iter().filter(|p| p.ghost_of.is_none()).count(), 4);
+    /// for ghost in points.iter().filter(|p| p.ghost_of.is_some()) {
+    ///     assert_eq!(ghost.sites, points[ghost.ghost_of.unwrap().0].sites);
+    /// }
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when the connections of a point are poisoned.
+    pub fn attachment_points(&self) -> impl Iterator<Item = AttachmentPoint> + '_ {
+        self.points
+            .iter()
+            .zip(self.ghost_parents())
+            .enumerate()
+            .map(|(number, (point, ghost_of))| AttachmentPoint {
+                index: LatticeIndex(number),
+                position: [point.x, point.y, point.z],
+                bond: point.bond,
+                ghost_of,
+                sites: point.get_connections().read().unwrap().clone(),
+            })
+    }
+
+    /// Iterate over the candidate sites of the `Lattice`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, Lattice, SiteKind};
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let tripoints = lattice
+    ///     .sites()
+    ///     .filter(|s| s.kind == SiteKind::Tripoint)
+    ///     .collect::<Vec<_>>();
+    /// assert!(tripoints.iter().all(|s| s.points.len() == 3));
+    /// assert_eq!(tripoints.len(), lattice.tripoints_to_plot().0.len());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    pub fn sites(&self) -> impl Iterator<Item = CandidateSite<'_>> {
+        (0..self.oxygens.len()).filter_map(|number| self.site(OxygenIndex(number)))
+    }
+
+    /// The candidate site with the given index, if it exists.
+    pub fn site(&self, index: OxygenIndex) -> Option<CandidateSite<'_>> {
+        self.oxygens.get(index.0).map(|oxygen| CandidateSite {
+            index,
+            kind: oxygen.sitetype.kind.clone(),
+            position: oxygen.position(),
+            points: oxygen.sitetype.as_slice(),
+            exclusions: &oxygen.exclusions,
+        })
+    }
+
+    /// The cell vectors of the `Lattice`, if it has a basis.
+    pub const fn basis(&self) -> Option<[[f32; 3]; 3]> {
+        self.basis
+    }
+
+    /// The ASE json structure the `Lattice` was created from.
+    pub const fn source_file(&self) -> Option<&JsonValue> {
+        self.source_file.as_ref()
+    }
+}
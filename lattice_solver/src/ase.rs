@@ -0,0 +1,101 @@
+//! Helpers for reading the json files written by ASE.
+use itertools::Itertools;
+use json::JsonValue;
+
+use crate::LatticeError;
+
+/// Returns the last structure stored in an ASE json database.
+pub fn last_structure(parsed: &JsonValue) -> Result<&JsonValue, LatticeError> {
+    let ids = &parsed["ids"];
+    if !ids.is_array() || ids.is_empty() {
+        return Err(LatticeError::MissingKey("ids".into()));
+    }
+    let last_id = ids[ids.len() - 1].to_string();
+    let structure = &parsed[last_id.as_str()];
+    if structure.is_null() {
+        return Err(LatticeError::MissingKey(last_id));
+    }
+    Ok(structure)
+}
+
+/// Returns the flat data of the `__ndarray__` stored under `key`.
+fn ndarray<'a>(structure: &'a JsonValue, key: &str) -> Result<&'a JsonValue, LatticeError> {
+    if structure[key].is_null() {
+        return Err(LatticeError::MissingKey(key.into()));
+    }
+    let data = &structure[key]["__ndarray__"][2];
+    if data.is_array() {
+        Ok(data)
+    } else {
+        Err(LatticeError::MalformedArray(key.into()))
+    }
+}
+
+/// Reads the atom positions of an ASE structure.
+pub fn positions(structure: &JsonValue) -> Result<Vec<[f32; 3]>, LatticeError> {
+    let values = ndarray(structure, "positions")?
+        .members()
+        .map(JsonValue::as_f32)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| LatticeError::MalformedArray("positions".into()))?;
+    if values.len() % 3 != 0 {
+        return Err(LatticeError::MalformedArray("positions".into()));
+    }
+    Ok(values
+        .chunks_exact(3)
+        .map(|v| [v[0], v[1], v[2]])
+        .collect_vec())
+}
+
+/// Reads the atomic numbers of an ASE structure.
+pub fn numbers(structure: &JsonValue) -> Result<Vec<i64>, LatticeError> {
+    ndarray(structure, "numbers")?
+        .members()
+        .map(JsonValue::as_i64)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| LatticeError::MalformedArray("numbers".into()))
+}
+
+/// Reads the tags of an ASE structure.
+pub fn tags(structure: &JsonValue) -> Result<Vec<i64>, LatticeError> {
+    ndarray(structure, "tags")?
+        .members()
+        .map(JsonValue::as_i64)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| LatticeError::MalformedArray("tags".into()))
+}
+
+/// Reads the periodic boundary conditions of an ASE structure.
+pub fn pbc(structure: &JsonValue) -> Result<[bool; 3], LatticeError> {
+    let values = ndarray(structure, "pbc")?
+        .members()
+        .map(JsonValue::as_bool)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| LatticeError::MalformedArray("pbc".into()))?;
+    match values[..] {
+        [x, y, z] => Ok([x, y, z]),
+        _ => Err(LatticeError::MalformedArray("pbc".into())),
+    }
+}
+
+/// Reads the three cell vectors of an ASE structure.
+pub fn cell(structure: &JsonValue) -> Result<[[f32; 3]; 3], LatticeError> {
+    if structure["cell"].is_null() {
+        return Err(LatticeError::MissingKey("cell".into()));
+    }
+    let cell = &structure["cell"]["array"]["__ndarray__"][2];
+    let shape_error = || LatticeError::CellShape(cell.dump());
+    let values = cell
+        .members()
+        .map(JsonValue::as_f32)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(shape_error)?;
+    if values.len() != 9 {
+        return Err(shape_error());
+    }
+    Ok([
+        [values[0], values[1], values[2]],
+        [values[3], values[4], values[5]],
+        [values[6], values[7], values[8]],
+    ])
+}
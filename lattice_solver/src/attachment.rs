@@ -0,0 +1,134 @@
+use json::JsonValue;
+
+use crate::{ase, LatticeError};
+
+/// The part of the slab in which attachment markers are searched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkerRegion {
+    /// Markers with `min <= z < max`.
+    Window(f32, f32),
+    /// Markers within the given distance of the highest atom of the slab.
+    Top(f32),
+    /// Markers within the given distance of the lowest atom of the slab.
+    Bottom(f32),
+}
+
+/// Describes which atoms of an ASE structure mark the attachment sites.
+///
+/// The same specification is used when importing a structure and when exporting
+/// a solution, so both agree on which atoms are replaced.
+///
+/// ```
+/// use crystacean_rs::{AttachmentSpec, MarkerRegion};
+///
+/// // Hydrogen markers in the top 3 Å of the slab, with ASE tag 1.
+/// let spec = AttachmentSpec::default()
+///     .region(MarkerRegion::Top(3.0))
+///     .tag(1);
+/// assert_eq!(spec.element, 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentSpec {
+    /// Atomic number of the marker atoms.
+    pub element: i64,
+    pub region: MarkerRegion,
+    /// When set, only atoms with these indices can be markers.
+    pub indices: Option<Vec<usize>>,
+    /// When set, only atoms with this ASE tag can be markers.
+    pub tag: Option<i64>,
+}
+
+impl Default for AttachmentSpec {
+    /// Hydrogen markers below `z = 20.0`.
+    fn default() -> Self {
+        Self {
+            element: 1,
+            region: MarkerRegion::Window(f32::NEG_INFINITY, 20.0),
+            indices: None,
+            tag: None,
+        }
+    }
+}
+
+impl AttachmentSpec {
+    pub fn element(self, element: i64) -> Self {
+        Self { element, ..self }
+    }
+
+    pub fn region(self, region: MarkerRegion) -> Self {
+        Self { region, ..self }
+    }
+
+    pub fn indices(self, indices: Vec<usize>) -> Self {
+        Self {
+            indices: Some(indices),
+            ..self
+        }
+    }
+
+    pub fn tag(self, tag: i64) -> Self {
+        Self {
+            tag: Some(tag),
+            ..self
+        }
+    }
+
+    /// Returns the indices of the marker atoms, given the atoms of a structure.
+    /// `tags` is only used when `self.tag` is set.
+    pub fn select(
+        &self,
+        positions: &[[f32; 3]],
+        numbers: &[i64],
+        tags: Option<&[i64]>,
+    ) -> Vec<usize> {
+        let heights = positions.iter().map(|p| p[2]);
+        let (min_z, max_z) = match self.region {
+            MarkerRegion::Window(min, max) => (min, max),
+            MarkerRegion::Top(depth) => {
+                let top = heights.fold(f32::NEG_INFINITY, f32::max);
+                (top - depth, f32::INFINITY)
+            }
+            MarkerRegion::Bottom(depth) => {
+                let bottom = heights.fold(f32::INFINITY, f32::min);
+                (f32::NEG_INFINITY, bottom + depth)
+            }
+        };
+
+        positions
+            .iter()
+            .zip(numbers)
+            .enumerate()
+            .filter(|(_, (position, number))| {
+                **number == self.element && min_z <= position[2] && position[2] < max_z
+            })
+            .filter(|(index, _)| {
+                self.indices
+                    .as_ref()
+                    .is_none_or(|indices| indices.contains(index))
+            })
+            .filter(|(index, _)| {
+                self.tag
+                    .is_none_or(|tag| tags.and_then(|t| t.get(*index)) == Some(&tag))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Returns the indices of the marker atoms in an ASE structure.
+    ///
+    /// # Errors
+    /// Returns an error when the structure lacks positions, numbers or, when
+    /// `self.tag` is set, tags.
+    pub fn select_in(&self, structure: &JsonValue) -> Result<Vec<usize>, LatticeError> {
+        let positions = ase::positions(structure)?;
+        let numbers = ase::numbers(structure)?;
+        if positions.len() != numbers.len() {
+            return Err(LatticeError::MalformedArray("numbers".into()));
+        }
+        let tags = match self.tag {
+            Some(_) => Some(ase::tags(structure)?),
+            None => None,
+        };
+        Ok(self.select(&positions, &numbers, tags.as_deref()))
+    }
+}
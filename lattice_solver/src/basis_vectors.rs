@@ -0,0 +1,95 @@
+//! Generation of flat silicon carbide attachment lattices from their basis vectors.
+//!
+//! Port of `full_lattice_from_basis_vectors` in `basis_vectors.py`.
+use crate::InputPoint;
+
+/// First basis vector of the silicon carbide attachment lattice.
+pub const SIC_A: [f32; 3] = [3.076, 0.0, 0.0];
+/// Second basis vector of the silicon carbide attachment lattice.
+pub const SIC_B: [f32; 3] = [1.538, 2.6638941420409337, 0.0];
+
+#[allow(clippy::cast_precision_loss)]
+fn combine(n: isize, a: [f32; 3], m: isize, b: [f32; 3]) -> [f32; 3] {
+    let (n, m) = (n as f32, m as f32);
+    [
+        n.mul_add(a[0], m * b[0]),
+        n.mul_add(a[1], m * b[1]),
+        n.mul_add(a[2], m * b[2]),
+    ]
+}
+
+fn add(one: [f32; 3], two: [f32; 3]) -> [f32; 3] {
+    [one[0] + two[0], one[1] + two[1], one[2] + two[2]]
+}
+
+/// The cell vectors of a supercell of `size_x` by `size_y` unit cells.
+///
+/// Every unit cell contains four attachment points and spans `2a` by `2b - a`.
+/// The lattice is flat, so the third cell vector is zero.
+#[allow(clippy::cast_possible_wrap)]
+pub fn supercell(size_x: usize, size_y: usize, a: [f32; 3], b: [f32; 3]) -> [[f32; 3]; 3] {
+    let (size_x, size_y) = (size_x as isize, size_y as isize);
+    [
+        combine(2 * size_x, a, 0, b),
+        combine(-size_y, a, 2 * size_y, b),
+        [0.0, 0.0, 0.0],
+    ]
+}
+
+/// Create the points and ghost images of a supercell of `size_x` by `size_y`
+/// unit cells, in the format accepted by `Lattice::python_new`.
+///
+/// Points on the bottom and left boundaries get ghosts on the opposite
+/// boundary, the origin also gets one in the opposite corner.
+///
+/// ```
+/// use crystacean_rs::basis_vectors::lattice_from_basis_vectors;
+///
+/// let points = lattice_from_basis_vectors(2, 3, [1.5, 0.0, 0.0], [0.75, 1.3, 0.0]);
+/// assert_eq!(points.len(), 4 * 2 * 3);
+/// ```
+#[allow(clippy::cast_possible_wrap)]
+pub fn lattice_from_basis_vectors(
+    size_x: usize,
+    size_y: usize,
+    a: [f32; 3],
+    b: [f32; 3],
+) -> Vec<InputPoint> {
+    let [vec_x, vec_y, _] = supercell(size_x, size_y, a, b);
+    let mut points = vec![];
+
+    for x in 0..size_x as isize {
+        for y in 0..size_y as isize {
+            let left_bottom = combine(2 * x - y, a, 2 * y, b);
+            let right_bottom = combine(2 * x - y + 1, a, 2 * y, b);
+            let left_top = combine(2 * x - y, a, 2 * y + 1, b);
+            let right_top = combine(2 * x - y + 1, a, 2 * y + 1, b);
+
+            let mut ghosts = [vec![], vec![], vec![], vec![]];
+            // link bottom boundary to the top
+            if y == 0 {
+                ghosts[0].push(add(right_bottom, vec_y));
+                ghosts[1].push(add(left_bottom, vec_y));
+            }
+            // link left boundary to the right
+            if x == 0 {
+                ghosts[1].push(add(left_bottom, vec_x));
+                ghosts[3].push(add(left_top, vec_x));
+                if y == 0 {
+                    ghosts[1].push(add(add(left_bottom, vec_x), vec_y));
+                }
+            }
+
+            for (point, ghosts) in [right_bottom, left_bottom, right_top, left_top]
+                .into_iter()
+                .zip(ghosts)
+            {
+                points.push((
+                    point.to_vec(),
+                    ghosts.into_iter().map(|g| g.to_vec()).collect(),
+                ));
+            }
+        }
+    }
+    points
+}
@@ -1,17 +1,39 @@
 use fixedbitset::FixedBitSet;
+use std::cmp::Ordering;
 
 mod bit_rep_impl;
+mod checkpoint;
+mod counts;
+mod depth_first;
+mod distances;
+mod limits;
+mod parallel_depth_first;
+mod report;
+mod sharded_filter;
+mod site_set;
 use crate::*;
+pub use checkpoint::Checkpoints;
+pub use counts::SolutionCounts;
+pub use depth_first::{DepthFirstSearch, SolutionIter};
+pub use distances::SiteDistances;
+pub use limits::{CancelToken, SolveLimits, SolveOutcome, SolveStatus};
+pub use report::{DepthStatistics, SolveReport};
+use site_set::SiteTablesCache;
 
 pub struct BitArrayRepresentation {
-    pub filled_sites: FixedBitSet,
-    pub exclusion_matrix: Vec<FixedBitSet>,
-    pub distances_matrix: Vec<Vec<f32>>,
-    pub tripoint_mask: FixedBitSet,
-    pub midpoint_mask: FixedBitSet,
-    pub singlet_mask: FixedBitSet,
+    // `filled_sites`, `exclusion_matrix` and `kind_masks` are private and
+    // only read through their accessors, as `site_tables` is built from them
+    // and would go stale if they changed after construction.
+    filled_sites: FixedBitSet,
+    exclusion_matrix: Vec<FixedBitSet>,
+    /// The distances between the sites, which the solve filters compare.
+    pub distances: SiteDistances,
+    /// For every kind of site, the sites of that kind.
+    kind_masks: Vec<(SiteKind, FixedBitSet)>,
     pub filter: Option<FixedBitSet>,
     pub options: BitArraySettings,
+    /// The representation in the forms the searches use, built on first use.
+    site_tables: SiteTablesCache,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -21,6 +43,7 @@ pub struct BitArraySettings {
     pub max_x: f32,
     pub max_y: f32,
     pub solve_filter: BitArrayFilter,
+    pub strategy: SolveStrategy,
 }
 
 impl BitArraySettings {
@@ -36,9 +59,15 @@ impl BitArraySettings {
             max_x: max.0,
             max_y: max.1,
             solve_filter,
+            strategy: SolveStrategy::BreadthFirst,
         }
     }
 
+    /// Builder method to change the `SolveStrategy` of the settings.
+    pub const fn with_strategy(self, strategy: SolveStrategy) -> Self {
+        Self { strategy, ..self }
+    }
+
     pub fn default(lattice: &Lattice) -> Self {
         let max = lattice.find_max();
         Self {
@@ -47,6 +76,7 @@ impl BitArraySettings {
             max_x: max.0,
             max_y: max.1,
             solve_filter: BitArrayFilter::default(),
+            strategy: SolveStrategy::default(),
         }
     }
 }
@@ -57,6 +87,7 @@ pub struct SettingsBuilder {
     difference_distance: Option<f32>,
     max: Option<(f32, f32)>,
     solve_filter: Option<BitArrayFilter>,
+    strategy: Option<SolveStrategy>,
 }
 
 impl SettingsBuilder {
@@ -66,6 +97,7 @@ impl SettingsBuilder {
             difference_distance: self.difference_distance,
             max: self.max,
             solve_filter: self.solve_filter,
+            strategy: self.strategy,
         }
     }
     pub const fn difference_distance(self, value: f32) -> Self {
@@ -74,6 +106,7 @@ impl SettingsBuilder {
             difference_distance: Some(value),
             max: self.max,
             solve_filter: self.solve_filter,
+            strategy: self.strategy,
         }
     }
     pub const fn max(self, value: (f32, f32)) -> Self {
@@ -82,6 +115,7 @@ impl SettingsBuilder {
             difference_distance: self.difference_distance,
             max: Some(value),
             solve_filter: self.solve_filter,
+            strategy: self.strategy,
         }
     }
     pub const fn solve_filter(self, value: BitArrayFilter) -> Self {
@@ -90,6 +124,16 @@ impl SettingsBuilder {
             difference_distance: self.difference_distance,
             max: self.max,
             solve_filter: Some(value),
+            strategy: self.strategy,
+        }
+    }
+    pub const fn strategy(self, value: SolveStrategy) -> Self {
+        Self {
+            max_singlets: self.max_singlets,
+            difference_distance: self.difference_distance,
+            max: self.max,
+            solve_filter: self.solve_filter,
+            strategy: Some(value),
         }
     }
     pub fn build(self, lattice: &Lattice) -> BitArraySettings {
@@ -99,6 +143,7 @@ impl SettingsBuilder {
             self.max.unwrap_or_else(|| lattice.find_max()),
             self.solve_filter.unwrap_or_default(),
         )
+        .with_strategy(self.strategy.unwrap_or_default())
     }
 }
 
@@ -109,6 +154,7 @@ impl SettingsBuilder {
 ///  - `difference_distance`: `f32`
 ///  - `max`: `(f32, f32)`
 ///  - `solve_filter`: `BitArrayFilter`
+///  - `strategy`: `SolveStrategy`
 macro_rules! bit_array_settings {
     ( $latt:expr, $($setter_method: ident = $value: expr),*) => {
         // use crystacean_rs::SettingsBuilder;
@@ -128,9 +174,51 @@ pub enum BitArrayFilter {
     InsideOut,
 }
 
+/// The order in which `BitArrayRepresentation::solve` explores the candidate
+/// structures. Both strategies find the same solutions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SolveStrategy {
+    /// Explore all structures with the same amount of sites together. Keeps
+    /// every structure of the current depth in memory.
+    #[default]
+    BreadthFirst,
+    /// Explore one structure at a time, see
+    /// `BitArrayRepresentation::solve_depth_first`. Memory use scales with the
+    /// depth of the search.
+    DepthFirst,
+}
+
 #[derive(Debug, PartialEq, Eq, Default)]
 pub struct BitArraySolution(pub FixedBitSet);
 
+/// The canonical order of solutions, in which the solvers return them: fewest
+/// sites first, then by the lowest differing site.
+///
+/// ```
+/// use crystacean_rs::BitArraySolution;
+/// use fixedbitset::FixedBitSet;
+///
+/// let solution = |blocks| BitArraySolution(FixedBitSet::with_capacity_and_blocks(4, vec![blocks]));
+/// let mut solutions = vec![solution(0b0111), solution(0b1010), solution(0b0101)];
+/// solutions.sort();
+/// assert_eq!(solutions, vec![solution(0b0101), solution(0b1010), solution(0b0111)]);
+/// ```
+impl Ord for BitArraySolution {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .count_ones(..)
+            .cmp(&other.0.count_ones(..))
+            .then_with(|| self.0.ones().cmp(other.0.ones()))
+            .then_with(|| self.0.len().cmp(&other.0.len()))
+    }
+}
+
+impl PartialOrd for BitArraySolution {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl BitArraySolution {
     /// Convert a solution from a filtered `BitArrayRepresentation` back into
     /// it's full form.
@@ -140,12 +228,12 @@ impl BitArraySolution {
     /// use fixedbitset::FixedBitSet;
     ///
     /// let mut compressed = BitArraySolution(
-    ///     FixedBitSet::with_capacity_and_blocks(4, vec![0b10110])
+    ///     FixedBitSet::with_capacity_and_blocks(5, vec![0b10110])
     /// );
     /// let full = BitArraySolution(
-    ///     FixedBitSet::with_capacity_and_blocks(6, vec![0b1001010000])
+    ///     FixedBitSet::with_capacity_and_blocks(10, vec![0b1001010000])
     /// );
-    /// let filter = FixedBitSet::with_capacity_and_blocks(6, vec![0b1101011000]);
+    /// let filter = FixedBitSet::with_capacity_and_blocks(10, vec![0b1101011000]);
     ///
     /// compressed.inflate(&filter);
     /// assert_eq!(compressed, full);
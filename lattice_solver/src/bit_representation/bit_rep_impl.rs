@@ -1,43 +1,98 @@
 use close_vector_tree::CloseVectorTreeMap;
 use fixedbitset::FixedBitSet;
 use itertools::Itertools;
-use kdam::{par_tqdm, tqdm, Colour, Spinner};
+use kdam::{tqdm, BarExt, Colour, Spinner};
 use ordered_float::NotNan;
-use scc::Bag;
 use std::{
     collections::HashMap,
     io::{stderr, IsTerminal},
-    mem,
+    ops::ControlFlow,
+    time::Instant,
 };
 
+use super::{
+    checkpoint::{Checkpointer, SolveState, Solver},
+    depth_first::Search,
+    limits::Limiter,
+    parallel_depth_first::ParallelSearch,
+    report::filter_memory,
+    sharded_filter::ShardedFilter,
+    site_set::{self, with_site_set, SiteSet, SiteTablesCache},
+};
 use crate::*;
 use rayon::prelude::*;
 
+/// The amount of structures of a generation which `solve_parallel` expands
+/// at once, before their children are filtered.
+const EXPANSION_CHUNK: usize = 1 << 14;
+
 impl BitArrayRepresentation {
+    /// A `BitArrayRepresentation` of which the search tables are built on
+    /// first use.
+    pub(crate) const fn from_parts(
+        filled_sites: FixedBitSet,
+        exclusion_matrix: Vec<FixedBitSet>,
+        distances: SiteDistances,
+        kind_masks: Vec<(SiteKind, FixedBitSet)>,
+        filter: Option<FixedBitSet>,
+        options: BitArraySettings,
+    ) -> Self {
+        Self {
+            filled_sites,
+            exclusion_matrix,
+            distances,
+            kind_masks,
+            filter,
+            options,
+            site_tables: SiteTablesCache::new(),
+        }
+    }
+
     /// Create a `BitArrayRepresentation` for testing purpouses.
     // #[cfg(doctest)]
     #[must_use]
     #[allow(clippy::too_many_arguments)]
-    pub fn create_debug(
+    pub const fn create_debug(
         filled_sites: FixedBitSet,
         exclusion_matrix: Vec<FixedBitSet>,
         distances_matrix: Vec<Vec<f32>>,
-        tripoint_mask: FixedBitSet,
-        midpoint_mask: FixedBitSet,
-        singlet_mask: FixedBitSet,
+        kind_masks: Vec<(SiteKind, FixedBitSet)>,
         filter: Option<FixedBitSet>,
         options: BitArraySettings,
     ) -> Self {
-        Self {
+        Self::from_parts(
             filled_sites,
             exclusion_matrix,
-            distances_matrix,
-            tripoint_mask,
-            midpoint_mask,
-            singlet_mask,
+            SiteDistances::from_matrix(distances_matrix),
+            kind_masks,
             filter,
             options,
-        }
+        )
+    }
+
+    /// The amount of sites of every kind in `vector`, in the order of
+    /// `self.kind_masks`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, BitArraySettings, Lattice, SiteKind};
+    /// # use fixedbitset::FixedBitSet;
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let kinds = intermediary.kind_masks().iter().map(|(kind, _)| kind.clone()).collect::<Vec<_>>();
+    /// assert_eq!(kinds, SiteKind::BUILT_IN);
+    ///
+    /// let mut all_sites = FixedBitSet::with_capacity(intermediary.filled_sites().len());
+    /// all_sites.insert_range(..);
+    /// let counts = intermediary.kind_counts(&all_sites);
+    /// assert_eq!(counts.iter().sum::<usize>(), lattice.sites().count());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    #[must_use]
+    pub fn kind_counts(&self, vector: &FixedBitSet) -> Vec<usize> {
+        self.kind_masks
+            .iter()
+            .map(|(_, mask)| vector.intersection_count(mask))
+            .collect()
     }
 
     /// Performes a binary matrix-vector multiply between `self.exclusion_matrix`
@@ -53,7 +108,7 @@ impl BitArrayRepresentation {
     ///
     /// ## Example:
     /// ```
-    /// # use crystacean_rs::{BitArraySolution, BitArrayRepresentation};
+    /// # use crystacean_rs::{BitArrayFilter, BitArrayRepresentation, BitArraySettings};
     /// # use fixedbitset::FixedBitSet;
     /// #
     /// let bit_array_repr = BitArrayRepresentation::create_debug(
@@ -70,10 +125,10 @@ impl BitArrayRepresentation {
     ///     #     FixedBitSet::with_capacity_and_blocks(5, vec![0b01100]),
     ///     #     FixedBitSet::with_capacity_and_blocks(5, vec![0b10000]),
     ///     # ],
-    ///     # FixedBitSet::new(),
-    ///     # FixedBitSet::new(),
-    ///     # FixedBitSet::new(),
-    ///     # None
+    ///     # vec![],
+    ///     # vec![],
+    ///     # None,
+    ///     # BitArraySettings::create(2, 0.05, (0.0, 0.0), BitArrayFilter::None),
     /// );
     ///
     /// let potential_solution = FixedBitSet::with_capacity_and_blocks(5, vec![0b11000]);
@@ -103,9 +158,9 @@ impl BitArrayRepresentation {
     ///
     /// The valid sites are determined by:
     /// 1. Obtaining available sites by using `self.matrix_vector_multiply`.
-    /// 1. available sites are checked with tripoint and midpoint mask.
-    ///     - if tripoints or midpoints are available, singlet possibilities are
-    ///       masked out before the results vector is returned.
+    /// 1. available sites are checked with the masks of all kinds except singlets.
+    ///     - if sites other than singlets are available, singlet possibilities
+    ///       are masked out before the results vector is returned.
     ///
     /// There are 5 output scenarios:
     /// # Outputs
@@ -118,11 +173,12 @@ impl BitArrayRepresentation {
     ///  - possibilities is empty after `rightmost_mask` -> `Err(())`
     ///  - tri/mid `masked_possibilities` is empty
     ///      - available singlets >  `self.max_singlets` -> `Err(())`
+    ///
     /// Vectors which return `Err(())` should be ignored.
     ///
     /// ## Example:
     /// ```
-    /// # use crystacean_rs::{BitArraySolution, BitArrayRepresentation};
+    /// # use crystacean_rs::{BitArrayFilter, BitArrayRepresentation, BitArraySettings, SiteKind};
     /// # use fixedbitset::FixedBitSet;
     /// #
     /// let bit_array_repr = BitArrayRepresentation::create_debug(
@@ -139,11 +195,15 @@ impl BitArrayRepresentation {
     ///     #     FixedBitSet::with_capacity_and_blocks(5, vec![0b01100]),
     ///     #     FixedBitSet::with_capacity_and_blocks(5, vec![0b10000]),
     ///     # ],
+    ///     # vec![],
     ///     // tripoint, midpoint and singlet masks:
-    ///     FixedBitSet::with_capacity_and_blocks(5, vec![0b00011]),
-    ///     FixedBitSet::with_capacity_and_blocks(5, vec![0b01100]),
-    ///     FixedBitSet::with_capacity_and_blocks(5, vec![0b10000]),
-    ///     # None
+    ///     vec![
+    ///         (SiteKind::Tripoint, FixedBitSet::with_capacity_and_blocks(5, vec![0b00011])),
+    ///         (SiteKind::Midpoint, FixedBitSet::with_capacity_and_blocks(5, vec![0b01100])),
+    ///         (SiteKind::Singlet, FixedBitSet::with_capacity_and_blocks(5, vec![0b10000])),
+    ///     ],
+    ///     # None,
+    ///     # BitArraySettings::create(2, 0.05, (0.0, 0.0), BitArrayFilter::None),
     /// );
     ///
     /// let tripoint_possible =
@@ -161,37 +221,56 @@ impl BitArrayRepresentation {
     /// assert_eq!(tripoint_impossible_sites, Ok(tripoint_impossible_answer));
     /// ```
     pub fn get_possibilities(&self, vector: &FixedBitSet) -> Result<FixedBitSet, &str> {
-        let non_singlet_mask: FixedBitSet = &self.tripoint_mask | &self.midpoint_mask;
-        let rightmost_bit = vector.maximum();
-
-        // If no possibilities found here, vector is solution
-        let mut possibilities: FixedBitSet = self.matrix_vector_multiply(vector);
-
-        // possible_sites can be reused later
-        let possible_sites = possibilities.count_ones(..);
-        if possible_sites == 0 {
-            return Ok(possibilities);
-        };
+        self.possibilities_from(vector, self.matrix_vector_multiply(vector))
+    }
 
-        // Mask of bits covered in other threads. If a vector is empty after this, all
-        // possible following states of this vector will be covered by other threads.
-        if let Some(mask) = rightmost_bit {
-            possibilities.set_range(..mask, false);
-        };
-        if possibilities.is_clear() {
-            return Err("Vector is invalid.");
-        };
+    /// Like `get_possibilities`, for a `vector` of which the `available`
+    /// sites, as given by `matrix_vector_multiply`, are already known. A
+    /// search can keep these up to date with `available_after` instead of
+    /// checking every row of the exclusion matrix for every structure.
+    ///
+    /// Only the depth-first searches and `count_solutions` do so. The
+    /// breadth-first solves still use `get_possibilities`, as keeping the
+    /// available sites of a whole generation would double its memory and the
+    /// size of its checkpoints.
+    ///
+    /// # Errors
+    /// The same as `get_possibilities`.
+    pub fn possibilities_from(
+        &self,
+        vector: &FixedBitSet,
+        available: FixedBitSet,
+    ) -> Result<FixedBitSet, &str> {
+        site_set::possibilities(
+            vector,
+            available,
+            self.non_singlet_mask(),
+            self.options.max_singlets,
+        )
+    }
 
-        let masked_possibilities = &possibilities & &non_singlet_mask;
-        if masked_possibilities.is_clear() {
-            if possible_sites <= self.options.max_singlets {
-                Ok(possibilities)
-            } else {
-                Err("Vector is invalid.")
-            }
-        } else {
-            Ok(masked_possibilities)
-        }
+    /// The sites which are still available after filling `site` of a
+    /// structure with `available` sites. Filling a site only takes away the
+    /// sites of its row of the exclusion matrix, which is symmetric.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let mut vector = intermediary.get_bitarray();
+    /// let available = intermediary.matrix_vector_multiply(&vector);
+    /// vector.insert(3);
+    /// assert_eq!(
+    ///     intermediary.available_after(&available, 3),
+    ///     intermediary.matrix_vector_multiply(&vector)
+    /// );
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    #[must_use]
+    pub fn available_after(&self, available: &FixedBitSet, site: usize) -> FixedBitSet {
+        let mut available = available.clone();
+        available.difference_with(&self.exclusion_matrix[site]);
+        available
     }
 
     /// Returns a clone of `self.filled_sites`.
@@ -200,6 +279,24 @@ impl BitArrayRepresentation {
         self.filled_sites.clone()
     }
 
+    /// The sites which are already filled, with one bit for every site.
+    #[must_use]
+    pub const fn filled_sites(&self) -> &FixedBitSet {
+        &self.filled_sites
+    }
+
+    /// For every site, the sites which can no longer be filled once it is.
+    #[must_use]
+    pub fn exclusion_matrix(&self) -> &[FixedBitSet] {
+        &self.exclusion_matrix
+    }
+
+    /// For every kind of site, the sites of that kind.
+    #[must_use]
+    pub fn kind_masks(&self) -> &[(SiteKind, FixedBitSet)] {
+        &self.kind_masks
+    }
+
     #[must_use]
     pub fn filtered(&self, filter: site_filter::SiteFilter) -> Self {
         // println!("{filter:?}");
@@ -212,42 +309,37 @@ impl BitArrayRepresentation {
 
         let filled_sites = FixedBitSet::with_capacity(new_length);
 
-        let mut tripoint_mask = FixedBitSet::with_capacity(new_length);
-        let mut midpoint_mask = FixedBitSet::with_capacity(new_length);
-        let mut singlet_mask = FixedBitSet::with_capacity(new_length);
+        let mut kind_masks = self
+            .kind_masks
+            .iter()
+            .map(|(kind, _)| (kind.clone(), FixedBitSet::with_capacity(new_length)))
+            .collect_vec();
 
         let mut exclusion_matrix = vec![];
-        let mut distances_matrix = vec![];
 
         for (new_number, old_number) in filter_set.ones().enumerate() {
-            tripoint_mask.set(new_number, self.tripoint_mask[old_number]);
-            midpoint_mask.set(new_number, self.midpoint_mask[old_number]);
-            singlet_mask.set(new_number, self.singlet_mask[old_number]);
+            for ((_, new_mask), (_, old_mask)) in kind_masks.iter_mut().zip(&self.kind_masks) {
+                new_mask.set(new_number, old_mask[old_number]);
+            }
 
             let mut new_matrix_row = FixedBitSet::with_capacity(new_length);
-            let mut new_distances_row = vec![];
             for (col_number, old_col_number) in filter_set.ones().enumerate() {
                 new_matrix_row.set(
                     col_number,
                     self.exclusion_matrix[old_number][old_col_number],
                 );
-
-                new_distances_row.push(self.distances_matrix[old_number][old_col_number]);
             }
             exclusion_matrix.push(new_matrix_row);
-            distances_matrix.push(new_distances_row);
         }
 
-        Self {
+        Self::from_parts(
             filled_sites,
             exclusion_matrix,
-            distances_matrix,
-            tripoint_mask,
-            midpoint_mask,
-            singlet_mask,
-            filter: Some(filter_set),
-            options: self.options,
-        }
+            self.distances.select(&filter_set.ones().collect_vec()),
+            kind_masks,
+            Some(filter_set),
+            self.options,
+        )
     }
 
     // /// Starts the solving process.
@@ -322,32 +414,140 @@ impl BitArrayRepresentation {
     /// Starts the solving process.
     ///
     /// `find_all` can be set to `true` to find all
+    ///
+    /// The solutions are returned in the canonical order of
+    /// `BitArraySolution`, which is the same for every solver and every run.
+    ///
+    /// Uses `solve_depth_first` when the `SolveStrategy` of the settings is
+    /// `DepthFirst`.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
     #[must_use]
     pub fn solve(&self, find_all: bool, silent: bool) -> Vec<BitArraySolution> {
-        let test_lattice = self.filled_sites.clone();
+        self.solve_limited(find_all, silent, &SolveLimits::NONE)
+            .solutions
+    }
 
-        let mut current_generation = vec![test_lattice];
-        let mut next_generation = vec![];
-        let mut depth = 0;
-        let mut solutions = vec![];
-        kdam::term::init(stderr().is_terminal());
+    /// Solve like `solve`, but stop early when one of the `limits` is reached.
+    /// The outcome holds the solutions found until then, and why the solve
+    /// stopped.
+    ///
+    /// The limits are checked after every explored structure, or once every
+    /// few hundred explored structures and after every solution when solving
+    /// depth-first.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, CancelToken, Lattice};
+    /// # use crystacean_rs::{SolveLimits, SolveStatus, SolveStrategy};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let outcome = intermediary.solve_limited(true, true, &SolveLimits::NONE.max_solutions(2));
+    /// assert_eq!(outcome.status, SolveStatus::MaxSolutions);
+    /// assert_eq!(outcome.solutions.len(), 2);
+    ///
+    /// let outcome = intermediary.solve_limited(true, true, &SolveLimits::NONE);
+    /// assert_eq!(outcome.status, SolveStatus::Complete);
+    ///
+    /// // A depth-first solve stops before its next solution is found.
+    /// let depth_first = lattice.get_intermediary(
+    ///     BitArraySettings::default(&lattice).with_strategy(SolveStrategy::DepthFirst),
+    /// );
+    /// let token = CancelToken::new();
+    /// token.cancel();
+    /// let outcome = depth_first.solve_limited(true, true, &SolveLimits::NONE.cancel_with(token));
+    /// assert_eq!(outcome.status, SolveStatus::Cancelled);
+    /// assert!(outcome.solutions.is_empty());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    #[must_use]
+    pub fn solve_limited(
+        &self,
+        find_all: bool,
+        silent: bool,
+        limits: &SolveLimits,
+    ) -> SolveOutcome {
+        if self.options.strategy == SolveStrategy::DepthFirst {
+            return self.solve_depth_first_limited(find_all, silent, limits);
+        }
+        self.solve_breadth_first(find_all, silent, None, limits)
+            .expect("solving without checkpoints does not access files")
+    }
 
-        while solutions.is_empty() | (find_all & (!next_generation.is_empty())) {
-            depth += 1;
+    /// Solve breadth-first like `solve`, while writing a checkpoint to
+    /// `checkpoints.path` at most once every `checkpoints.interval`. When
+    /// `checkpoints.resume` is set and the checkpoint exists, the solve
+    /// continues from it, and gives the same solutions as an uninterrupted
+    /// solve. Otherwise an existing checkpoint is replaced. The checkpoint is
+    /// removed when the solve is done.
+    ///
+    /// Always solves breadth-first, regardless of the `SolveStrategy`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, BitArraySettings, Checkpoints, Lattice};
+    /// # use std::time::Duration;
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let path = std::env::temp_dir().join("solve_checkpointed_doctest.json");
+    /// std::fs::write(&path, "not a checkpoint")?;
+    ///
+    /// // Resuming reads the existing file, which is not a valid checkpoint.
+    /// let checkpoints = Checkpoints::new(&path, Duration::from_secs(1));
+    /// assert!(intermediary.solve_checkpointed(true, true, &checkpoints.clone().resuming()).is_err());
+    ///
+    /// // Without resuming, the solve starts over.
+    /// let solutions = intermediary.solve_checkpointed(true, true, &checkpoints)?;
+    /// assert_eq!(solutions, intermediary.solve(true, true));
+    /// assert!(!path.exists());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when a checkpoint can not be written or read, or when
+    /// the existing checkpoint belongs to a different solve.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn solve_checkpointed(
+        &self,
+        find_all: bool,
+        silent: bool,
+        checkpoints: &Checkpoints,
+    ) -> Result<Vec<BitArraySolution>, LatticeError> {
+        self.solve_breadth_first(find_all, silent, Some(checkpoints), &SolveLimits::NONE)
+            .map(|outcome| outcome.solutions)
+    }
 
-            next_generation.clear();
+    /// When a limit stops the solve, a checkpoint is written so the solve can
+    /// be continued later.
+    fn solve_breadth_first(
+        &self,
+        find_all: bool,
+        silent: bool,
+        checkpoints: Option<&Checkpoints>,
+        limits: &SolveLimits,
+    ) -> Result<SolveOutcome, LatticeError> {
+        let mut state = SolveState::start(self, Solver::Sequential, find_all, checkpoints)?;
+        let mut checkpointer = Checkpointer::new(checkpoints);
+        let limiter = Limiter::new(limits);
+        let mut status = SolveStatus::Complete;
+        let mut report = SolveReport::new(self);
+        let started = Instant::now();
+        kdam::term::init(stderr().is_terminal());
 
-            let iterator = if silent {
-                tqdm!(
-                    current_generation.iter(),
-                    disable = true,
-                    position = 1,
-                    bar_format = ""
-                )
+        while state.should_continue() {
+            let depth_started = Instant::now();
+            let mut statistics = DepthStatistics::new(state.depth);
+            let total = state.current_generation.len();
+            let mut progress = if silent {
+                tqdm!(total = total, disable = true, position = 1, bar_format = "")
             } else {
                 tqdm!(
-                    current_generation.iter(),
-                    desc = format!("Current depth: {depth}"),
+                    total = total,
+                    desc = format!("Current depth: {}", state.depth),
                     mininterval = 1.0/60.0,
                     bar_format = "{desc suffix=' '}|{animation}| {spinner} {count}/{total} [{percentage:.0}%] in {elapsed human=true} ({rate:.1}/s, eta: {remaining human=true})",
                     colour = Colour::gradient(&["#FF0000", "#FFDD00"]),
@@ -359,53 +559,364 @@ impl BitArrayRepresentation {
                     leave = true
                 )
             };
-
-            let mut structure_map = HashMap::new();
-            let mut new_structure_map =
-                CloseVectorTreeMap::new(self.options.difference_distance.try_into().unwrap());
-
-            for candidate in iterator {
-                if let Ok(possibilities) = self.get_possibilities(candidate) {
-                    if possibilities.is_clear() {
-                        solutions.push(BitArraySolution(candidate.clone()));
-                        continue;
-                    }
-
-                    for fillable_site in possibilities.ones() {
-                        let mut new_candidate = candidate.clone();
-                        new_candidate.set(fillable_site, true);
-
-                        if self.solving_filter(
-                            &new_candidate,
-                            &mut structure_map,
-                            &mut new_structure_map,
-                        ) {
-                            next_generation.push(new_candidate);
-                        }
-                    }
+            // A resumed generation continues where the checkpoint was written.
+            let _ = progress.update(state.position);
+
+            while state.position < state.current_generation.len() {
+                self.explore_next(&mut state, &mut statistics);
+                state.position += 1;
+                let _ = progress.update(1);
+                if let Some(reached) =
+                    limiter.check(state.solutions.len(), state.next_generation.len())
+                {
+                    status = reached;
+                    break;
                 }
+                checkpointer.tick(self, &state)?;
             }
 
-            mem::swap(&mut current_generation, &mut next_generation);
+            statistics.elapsed = depth_started.elapsed();
+            report.depths.push(statistics);
+            report.observe_candidates(
+                state.current_generation.len()
+                    + state.next_generation.len()
+                    + state.solutions.len(),
+                self.filled_sites.len(),
+                filter_memory(&state.structure_map, &state.new_structure_map),
+            );
+            if status != SolveStatus::Complete {
+                break;
+            }
+            state.next_depth(self);
+        }
+        if status == SolveStatus::Complete {
+            checkpointer.finish()?;
+        } else {
+            checkpointer.write(self, &state)?;
+        }
+
+        let mut solutions = state
+            .solutions
+            .into_iter()
+            .map(BitArraySolution)
+            .collect_vec();
+        solutions.sort_unstable();
+        limiter.truncate(&mut solutions);
+        report.count_compositions(self, solutions.iter().map(|solution| &solution.0));
+        if let Some(filter) = &self.filter {
+            for solution in &mut solutions {
+                solution.inflate(filter);
+            }
+        }
+        report.elapsed = started.elapsed();
+        Ok(SolveOutcome {
+            solutions,
+            status,
+            report,
+        })
+    }
+
+    /// Explore the structure at `state.position` of the current generation of
+    /// a breadth-first solve.
+    fn explore_next(&self, state: &mut SolveState, statistics: &mut DepthStatistics) {
+        let candidate = &state.current_generation[state.position];
+        statistics.explored += 1;
+        let Ok(possibilities) = self.get_possibilities(candidate) else {
+            statistics.pruned_by_possibilities += 1;
+            return;
+        };
+        if possibilities.is_clear() {
+            state.solutions.push(candidate.clone());
+            statistics.solutions += 1;
+        }
+
+        for fillable_site in possibilities.ones() {
+            let mut new_candidate = candidate.clone();
+            new_candidate.set(fillable_site, true);
+
+            if self.solving_filter(
+                &new_candidate,
+                &mut state.structure_map,
+                &mut state.new_structure_map,
+            ) {
+                state.next_generation.push(new_candidate);
+            } else {
+                statistics.pruned_by_filter += 1;
+            }
+        }
+    }
+
+    /// Starts the solving process, exploring one candidate structure at a
+    /// time. Only the unexplored siblings of the current structure are kept,
+    /// so memory use scales with the depth of the search instead of the width
+    /// of a generation.
+    ///
+    /// On lattices with at most 512 candidate sites, every structure is stored
+    /// in a fixed-size array instead of a `FixedBitSet`, which saves an
+    /// allocation for every explored structure.
+    ///
+    /// Finds the same solutions as `solve`. When `find_all` is `false`, only
+    /// the solutions with the fewest sites are returned, as with `solve`.
+    /// Under a solve filter, the structures which are kept out of a group of
+    /// similar structures can differ from `solve`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// assert_eq!(intermediary.solve(true, true), intermediary.solve_depth_first(true, true));
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    #[must_use]
+    pub fn solve_depth_first(&self, find_all: bool, silent: bool) -> Vec<BitArraySolution> {
+        self.solve_depth_first_limited(find_all, silent, &SolveLimits::NONE)
+            .solutions
+    }
+
+    fn solve_depth_first_limited(
+        &self,
+        find_all: bool,
+        silent: bool,
+        limits: &SolveLimits,
+    ) -> SolveOutcome {
+        with_site_set!(self, Set => self.solve_depth_first_with::<Set>(find_all, silent, limits))
+    }
 
-            if solutions.is_empty() && current_generation.is_empty() {
+    /// Solve depth-first, with every structure stored as an `S`.
+    fn solve_depth_first_with<S: SiteSet>(
+        &self,
+        find_all: bool,
+        silent: bool,
+        limits: &SolveLimits,
+    ) -> SolveOutcome {
+        kdam::term::init(stderr().is_terminal());
+        let mut progress = tqdm!(
+            desc = "Solutions found",
+            disable = silent,
+            mininterval = 1.0 / 60.0,
+            bar_format = "{desc suffix=': '}{count} in {elapsed human=true} ({rate:.1}/s)",
+            leave = true
+        );
+
+        let limiter = Limiter::new(limits);
+        let mut status = SolveStatus::Complete;
+        let mut report = SolveReport::new(self);
+        let started = Instant::now();
+        let mut found = vec![];
+        // The amount of sites of the smallest solution found so far.
+        let mut fewest_sites = usize::MAX;
+        let mut search = Search::<S>::at_filled_sites(self);
+        loop {
+            let candidate = match search.next_candidate_checked(self, |search| {
+                limiter.check(found.len(), search.frontier_len())
+            }) {
+                Ok(Some(candidate)) => candidate,
+                Ok(None) => break,
+                Err(reached) => {
+                    status = reached;
+                    break;
+                }
+            };
+            let _ = progress.update(1);
+            if !find_all {
+                let sites = candidate.count_ones();
+                if sites < fewest_sites {
+                    found.clear();
+                    fewest_sites = sites;
+                    search.limit_sites(sites);
+                }
+            }
+            found.push(candidate);
+            if let Some(reached) = limiter.check(found.len(), search.frontier_len()) {
+                status = reached;
                 break;
             }
         }
+
+        report.depths = search.statistics().to_vec();
+        report.observe_candidates(
+            search.peak_frontier() + found.len(),
+            self.filled_sites.len(),
+            search.filter_memory(),
+        );
+        let found = found
+            .iter()
+            .map(|candidate| candidate.to_bitset(self.filled_sites.len()).into_owned())
+            .collect_vec();
+        report.count_compositions(self, &found);
+        let mut solutions = found.into_iter().map(BitArraySolution).collect_vec();
+        solutions.sort_unstable();
         if let Some(filter) = &self.filter {
             for solution in &mut solutions {
                 solution.inflate(filter);
             }
         }
-        // println!();
-        // println!();
-        solutions
+        report.elapsed = started.elapsed();
+        SolveOutcome {
+            solutions,
+            status,
+            report,
+        }
     }
 
-    fn solving_filter(
+    /// Iterate over all solutions, which are found depth-first while
+    /// iterating. The solutions are the same as those of `solve` with
+    /// `find_all`, and the search stops when the iterator is dropped.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let first_two = intermediary.solve_iter().take(2).collect::<Vec<_>>();
+    /// assert_eq!(first_two.len(), 2);
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn solve_iter(&self) -> SolutionIter<'_> {
+        SolutionIter::new(self)
+    }
+
+    /// Call `callback` with every solution as soon as it is found, until it
+    /// returns `ControlFlow::Break`. Finds the same solutions as `solve_iter`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// # use std::ops::ControlFlow;
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let mut found = 0;
+    /// intermediary.solve_with(|_solution| {
+    ///     found += 1;
+    ///     if found == 3 {
+    ///         ControlFlow::Break(())
+    ///     } else {
+    ///         ControlFlow::Continue(())
+    ///     }
+    /// });
+    /// assert_eq!(found, 3);
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn solve_with<F>(&self, mut callback: F)
+    where
+        F: FnMut(BitArraySolution) -> ControlFlow<()>,
+    {
+        for solution in self.solve_iter() {
+            if callback(solution).is_break() {
+                break;
+            }
+        }
+    }
+
+    /// Count all solutions by their composition, the amount of sites of every
+    /// kind in `self.kind_masks`, without keeping the solutions themselves.
+    /// Shows no progress bar.
+    ///
+    /// With `parallel`, the structures with one site more than the filled
+    /// sites are counted in parallel. A solve filter then only compares
+    /// structures grown from the same one, so more similar structures can be
+    /// counted than without `parallel`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArrayFilter, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let counts = intermediary.count_solutions(false);
+    /// assert_eq!(counts.total(), intermediary.solve(true, true).len());
+    /// assert_eq!(counts, intermediary.count_solutions(true));
+    ///
+    /// let settings = BitArraySettings {
+    ///     solve_filter: BitArrayFilter::Similarity,
+    ///     ..BitArraySettings::default(&lattice)
+    /// };
+    /// let filtered = lattice.get_intermediary(settings);
+    /// let serial = filtered.count_solutions(false);
+    /// let parallel = filtered.count_solutions(true);
+    /// assert_eq!(serial.total(), filtered.solve(true, true).len());
+    /// assert!(serial.total() < counts.total());
+    /// assert!((serial.total()..=counts.total()).contains(&parallel.total()));
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    #[must_use]
+    pub fn count_solutions(&self, parallel: bool) -> SolutionCounts {
+        with_site_set!(self, Set => self.count_solutions_with::<Set>(parallel))
+    }
+
+    /// Count all solutions, with every structure stored as an `S`.
+    fn count_solutions_with<S: SiteSet>(&self, parallel: bool) -> SolutionCounts {
+        let kinds = self
+            .kind_masks
+            .iter()
+            .map(|(kind, _)| kind.clone())
+            .collect_vec();
+        let tables = S::tables(self);
+        let count_from = |candidate: S, available: S| {
+            let mut counts = SolutionCounts::new(kinds.clone());
+            let mut search = Search::new(self, candidate, available);
+            while let Some(solution) = search.next_candidate(self) {
+                counts.add(tables.kind_counts(&solution));
+            }
+            counts
+        };
+
+        let filled_sites = S::from_bitset(&self.filled_sites);
+        let available = S::from_bitset(&self.matrix_vector_multiply(&self.filled_sites));
+        if !parallel {
+            return count_from(filled_sites, available);
+        }
+        let Ok(possibilities) = site_set::possibilities(
+            &filled_sites,
+            available.clone(),
+            &tables.non_singlet_mask,
+            self.options.max_singlets,
+        ) else {
+            return SolutionCounts::new(kinds);
+        };
+        if possibilities.is_clear() {
+            return count_from(filled_sites, available);
+        }
+        possibilities
+            .ones()
+            .collect_vec()
+            .into_par_iter()
+            .map(|fillable_site| {
+                let mut candidate = filled_sites.clone();
+                candidate.insert(fillable_site);
+                count_from(candidate, tables.available_after(&available, fillable_site))
+            })
+            .reduce(|| SolutionCounts::new(kinds.clone()), SolutionCounts::merge)
+    }
+
+    /// `solving_filter` for any kind of `SiteSet`, which is only turned into a
+    /// `FixedBitSet` when a solve filter is used.
+    pub(crate) fn solving_filter_for<S: SiteSet>(
+        &self,
+        new_candidate: &S,
+        structure_map: &mut HashMap<Vec<usize>, Vec<Vec<f32>>>,
+        new_structure_map: &mut CloseVectorTreeMap,
+    ) -> bool {
+        self.options.solve_filter == BitArrayFilter::None
+            || self.solving_filter(
+                &new_candidate.to_bitset(self.filled_sites.len()),
+                structure_map,
+                new_structure_map,
+            )
+    }
+
+    pub(crate) fn solving_filter(
         &self,
         new_candidate: &FixedBitSet,
-        structure_map: &mut HashMap<(usize, usize, usize), Vec<Vec<f32>>>,
+        structure_map: &mut HashMap<Vec<usize>, Vec<Vec<f32>>>,
         new_structure_map: &mut CloseVectorTreeMap,
     ) -> bool {
         match self.options.solve_filter {
@@ -420,19 +931,16 @@ impl BitArrayRepresentation {
     pub(crate) fn similarity_filter(
         &self,
         new_candidate: &FixedBitSet,
-        structure_map: &mut HashMap<(usize, usize, usize), Vec<Vec<f32>>>,
+        structure_map: &mut HashMap<Vec<usize>, Vec<Vec<f32>>>,
     ) -> bool {
-        let type_distribution = (
-            new_candidate.union_count(&self.tripoint_mask),
-            new_candidate.union_count(&self.midpoint_mask),
-            new_candidate.union_count(&self.singlet_mask),
-        );
+        let type_distribution = self.kind_counts(new_candidate);
 
-        let structure_vec: &Vec<Vec<f32>> = structure_map.entry(type_distribution).or_default();
+        let structure_vec: &Vec<Vec<f32>> =
+            structure_map.entry(type_distribution.clone()).or_default();
 
         let mut new_structure = vec![];
         for (one, two) in new_candidate.ones().combinations(2).map(|v| (v[0], v[1])) {
-            new_structure.push(self.distances_matrix[one][two]);
+            new_structure.push(self.distances.distance(one, two));
         }
         new_structure.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
 
@@ -452,19 +960,16 @@ impl BitArrayRepresentation {
     pub(crate) fn flipped_filter(
         &self,
         new_candidate: &FixedBitSet,
-        structure_map: &mut HashMap<(usize, usize, usize), Vec<Vec<f32>>>,
+        structure_map: &mut HashMap<Vec<usize>, Vec<Vec<f32>>>,
     ) -> bool {
-        let type_distribution = (
-            new_candidate.union_count(&self.tripoint_mask),
-            new_candidate.union_count(&self.midpoint_mask),
-            new_candidate.union_count(&self.singlet_mask),
-        );
+        let type_distribution = self.kind_counts(new_candidate);
 
-        let structure_vec: &Vec<Vec<f32>> = structure_map.entry(type_distribution).or_default();
+        let structure_vec: &Vec<Vec<f32>> =
+            structure_map.entry(type_distribution.clone()).or_default();
 
         let mut new_structure = vec![];
         for (one, two) in new_candidate.ones().combinations(2).map(|v| (v[0], v[1])) {
-            new_structure.push(self.distances_matrix[one][two]);
+            new_structure.push(self.distances.distance(one, two));
         }
         new_structure.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap().reverse());
 
@@ -484,19 +989,16 @@ impl BitArrayRepresentation {
     pub(crate) fn insideout_filter(
         &self,
         new_candidate: &FixedBitSet,
-        structure_map: &mut HashMap<(usize, usize, usize), Vec<Vec<f32>>>,
+        structure_map: &mut HashMap<Vec<usize>, Vec<Vec<f32>>>,
     ) -> bool {
-        let type_distribution = (
-            new_candidate.union_count(&self.tripoint_mask),
-            new_candidate.union_count(&self.midpoint_mask),
-            new_candidate.union_count(&self.singlet_mask),
-        );
+        let type_distribution = self.kind_counts(new_candidate);
 
-        let structure_vec: &Vec<Vec<f32>> = structure_map.entry(type_distribution).or_default();
+        let structure_vec: &Vec<Vec<f32>> =
+            structure_map.entry(type_distribution.clone()).or_default();
 
         let mut new_structure = vec![];
         for (one, two) in new_candidate.ones().combinations(2).map(|v| (v[0], v[1])) {
-            new_structure.push(self.distances_matrix[one][two]);
+            new_structure.push(self.distances.distance(one, two));
         }
         new_structure.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
         new_structure = {
@@ -536,7 +1038,7 @@ impl BitArrayRepresentation {
     pub fn create_diff_vector(&self, new_candidate: &FixedBitSet) -> Vec<NotNan<f32>> {
         let mut new_structure = vec![];
         for (one, two) in new_candidate.ones().combinations(2).map(|v| (v[0], v[1])) {
-            new_structure.push(NotNan::new(self.distances_matrix[one][two]).unwrap());
+            new_structure.push(NotNan::new(self.distances.distance(one, two)).unwrap());
         }
         new_structure.sort_unstable();
         new_structure
@@ -546,35 +1048,204 @@ impl BitArrayRepresentation {
     ///
     /// `find_all` can be set to `true` to find all
     ///
+    /// The structures of a generation are expanded in chunks, and the children
+    /// of every chunk are filtered before the next chunk is expanded. The
+    /// solve filter is applied per composition in parallel, which finds the
+    /// same solutions as `solve`. Filtering uses at most one thread per
+    /// composition, so it is only as parallel as the amount of compositions
+    /// with one more site.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArrayFilter, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let settings = BitArraySettings {
+    ///     solve_filter: BitArrayFilter::SimTrees,
+    ///     ..BitArraySettings::default(&lattice)
+    /// };
+    /// let intermediary = lattice.get_intermediary(settings);
+    /// assert_eq!(intermediary.solve_parallel(true, true), intermediary.solve(true, true));
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
     /// # Panics
     /// Could technically panic but I don't see that happening.
     #[must_use]
     pub fn solve_parallel(&self, find_all: bool, silent: bool) -> Vec<BitArraySolution> {
-        let test_lattice = self.filled_sites.clone();
+        self.solve_parallel_limited(find_all, silent, &SolveLimits::NONE)
+            .solutions
+    }
 
-        let mut current_generation = vec![test_lattice];
-        let mut next_generation = vec![];
-        let mut depth = 0;
-        // let mut solutions = Mutex::new(vec![]);
-        let solution_bag: Bag<BitArraySolution> = Bag::default();
+    /// Solve like `solve_parallel`, but stop early when one of the `limits` is
+    /// reached, see `solve_limited`. The amount of solutions and the frontier
+    /// are only checked between depths.
+    ///
+    /// Uses `solve_depth_first_parallel` when the `SolveStrategy` of the
+    /// settings is `DepthFirst`.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    #[must_use]
+    pub fn solve_parallel_limited(
+        &self,
+        find_all: bool,
+        silent: bool,
+        limits: &SolveLimits,
+    ) -> SolveOutcome {
+        if self.options.strategy == SolveStrategy::DepthFirst {
+            return self.solve_depth_first_parallel_limited(find_all, silent, limits);
+        }
+        self.solve_parallel_inner(find_all, silent, None, limits)
+            .expect("solving without checkpoints does not access files")
+    }
+
+    /// Solve depth-first like `solve_depth_first`, on all threads of the
+    /// rayon pool. Every structure close to the filled sites becomes a task,
+    /// which idle threads can steal, and deeper subtrees are searched
+    /// sequentially. Subtrees are split by the same rightmost site ordering
+    /// `get_possibilities` uses, so no structure is explored twice. Memory
+    /// use scales with the depth of the search times the amount of threads.
+    ///
+    /// When `find_all` is `false`, all tasks skip the structures with more
+    /// sites than the smallest solution any task has found. The limits are
+    /// checked while searching, like with `solve_depth_first`.
+    ///
+    /// Finds the same solutions as `solve_depth_first`. Under a solve filter,
+    /// subtrees are filtered independently and similar solutions of
+    /// different subtrees are removed at the end, so the structures which are
+    /// kept can differ from the other solvers, but not between runs.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// assert_eq!(
+    ///     intermediary.solve_depth_first_parallel(true, true),
+    ///     intermediary.solve_depth_first(true, true)
+    /// );
+    /// assert_eq!(
+    ///     intermediary.solve_depth_first_parallel(false, true),
+    ///     intermediary.solve_depth_first(false, true)
+    /// );
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    #[must_use]
+    pub fn solve_depth_first_parallel(
+        &self,
+        find_all: bool,
+        silent: bool,
+    ) -> Vec<BitArraySolution> {
+        self.solve_depth_first_parallel_limited(find_all, silent, &SolveLimits::NONE)
+            .solutions
+    }
+
+    /// The amount of solutions and the frontier of a single subtree are
+    /// checked after every solution.
+    fn solve_depth_first_parallel_limited(
+        &self,
+        find_all: bool,
+        silent: bool,
+        limits: &SolveLimits,
+    ) -> SolveOutcome {
         kdam::term::init(stderr().is_terminal());
+        let progress = tqdm!(
+            desc = "Solutions found",
+            disable = silent,
+            mininterval = 1.0 / 60.0,
+            bar_format = "{desc suffix=': '}{count} in {elapsed human=true} ({rate:.1}/s)",
+            leave = true
+        );
 
-        while solution_bag.is_empty() | (find_all & (!next_generation.is_empty())) {
-            depth += 1;
+        let limiter = Limiter::new(limits);
+        let mut report = SolveReport::new(self);
+        let started = Instant::now();
+        let (mut found, status) = with_site_set!(self, Set => {
+            let search = ParallelSearch::<Set>::new(self, find_all, &limiter, progress);
+            search.run();
+            search.finish(&mut report)
+        });
+        if !find_all {
+            // Every subtree only kept its own smallest solutions.
+            if let Some(fewest_sites) = found.iter().map(|found| found.count_ones(..)).min() {
+                found.retain(|found| found.count_ones(..) == fewest_sites);
+            }
+        }
 
-            next_generation.clear();
+        let mut solutions = found.into_iter().map(BitArraySolution).collect_vec();
+        solutions.sort_unstable();
+        let found = solutions.len();
+        let mut filter = ShardedFilter::new(self);
+        let solutions = filter.filter(solutions.into_iter().map(|solution| solution.0).collect());
+        report.observe_candidates(found, self.filled_sites.len(), filter.memory());
+        let mut solutions = solutions.into_iter().map(BitArraySolution).collect_vec();
+        limiter.truncate(&mut solutions);
+        report.count_compositions(self, solutions.iter().map(|solution| &solution.0));
+        if let Some(filter) = &self.filter {
+            for solution in &mut solutions {
+                solution.inflate(filter);
+            }
+        }
+        report.elapsed = started.elapsed();
+        SolveOutcome {
+            solutions,
+            status,
+            report,
+        }
+    }
 
-            let iterator = if silent {
-                par_tqdm!(
-                    current_generation.par_iter(),
-                    disable = true,
-                    position = 1,
-                    bar_format = ""
-                )
+    /// Solve like `solve_parallel`, while writing a checkpoint to
+    /// `checkpoints.path` after a generation when `checkpoints.interval` has
+    /// passed since the last one. When `checkpoints.resume` is set and the
+    /// checkpoint exists, the solve continues from it, and finds the same
+    /// solutions as an uninterrupted solve. The checkpoint is removed when
+    /// the solve is done.
+    ///
+    /// # Errors
+    /// Returns an error when a checkpoint can not be written or read, or when
+    /// the existing checkpoint belongs to a different solve.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn solve_parallel_checkpointed(
+        &self,
+        find_all: bool,
+        silent: bool,
+        checkpoints: &Checkpoints,
+    ) -> Result<Vec<BitArraySolution>, LatticeError> {
+        self.solve_parallel_inner(find_all, silent, Some(checkpoints), &SolveLimits::NONE)
+            .map(|outcome| outcome.solutions)
+    }
+
+    /// When a limit stops the solve between depths, a checkpoint is written so
+    /// the solve can be continued later. A depth which is cut short is not
+    /// written.
+    fn solve_parallel_inner(
+        &self,
+        find_all: bool,
+        silent: bool,
+        checkpoints: Option<&Checkpoints>,
+        limits: &SolveLimits,
+    ) -> Result<SolveOutcome, LatticeError> {
+        let mut state = SolveState::start(self, Solver::Parallel, find_all, checkpoints)?;
+        let mut checkpointer = Checkpointer::new(checkpoints);
+        let limiter = Limiter::new(limits);
+        let mut status = SolveStatus::Complete;
+        let mut report = SolveReport::new(self);
+        let started = Instant::now();
+        kdam::term::init(stderr().is_terminal());
+
+        while state.should_continue() {
+            let depth_started = Instant::now();
+            let mut statistics = DepthStatistics::new(state.depth);
+            let total = state.current_generation.len();
+            let mut progress = if silent {
+                tqdm!(total = total, disable = true, position = 1, bar_format = "")
             } else {
-                par_tqdm!(
-                    current_generation.par_iter(),
-                    desc = format!("Current depth: {depth}"),
+                tqdm!(
+                    total = total,
+                    desc = format!("Current depth: {}", state.depth),
                     mininterval = 1.0/60.0,
                     bar_format = "{desc suffix=' '}|{animation}| {spinner} {count}/{total} [{percentage:.0}%] in {elapsed human=true} ({rate:.1}/s, eta: {remaining human=true})",
                     colour = Colour::gradient(&["#0000FF", "#00FFFF"]),
@@ -587,43 +1258,122 @@ impl BitArrayRepresentation {
                 )
             };
 
-            // let structure_archive: scc::HashMap<(usize, usize, usize), Vec<Vec<f32>>> =
-            //     scc::HashMap::new();
-
-            next_generation = iterator
-                .map(|vector| (vector, self.get_possibilities(vector)))
-                .filter_map(|(v, c)| c.map_or_else(|_| None, |p| Some((v, p))))
-                .map(|(v, c)| {
-                    let mut new_candidates = vec![];
-                    for fillable_site in c.ones() {
-                        let mut new = v.clone();
-                        new.set(fillable_site, true);
-                        new_candidates.push(new);
-                    }
-                    (v, new_candidates)
-                })
-                .flat_map_iter(|(v, c)| {
-                    if c.is_empty() {
-                        solution_bag.push(BitArraySolution(v.clone()));
-                    }
-                    c
-                })
-                .collect();
-
-            mem::swap(&mut current_generation, &mut next_generation);
+            // The children of a chunk are filtered before the next chunk is
+            // expanded, so the unfiltered generation is never held at once.
+            let mut filter = ShardedFilter::new(self);
+            for parents in state.current_generation.chunks(EXPANSION_CHUNK) {
+                let (solutions, children) =
+                    self.expand_chunk(parents, &mut filter, &limiter, &mut statistics);
+                let _ = progress.update(parents.len());
+                state.solutions.extend(solutions);
+                state.next_generation.extend(children);
+                if limiter.interrupted().is_some() {
+                    break;
+                }
+            }
+
+            statistics.elapsed = depth_started.elapsed();
+            report.depths.push(statistics);
+            report.observe_candidates(
+                state.current_generation.len()
+                    + state.next_generation.len()
+                    + state.solutions.len(),
+                self.filled_sites.len(),
+                filter.memory(),
+            );
+            if let Some(reached) = limiter.interrupted() {
+                status = reached;
+                break;
+            }
+
+            state.next_depth(self);
+            if let Some(reached) =
+                limiter.check(state.solutions.len(), state.current_generation.len())
+            {
+                status = reached;
+                checkpointer.write(self, &state)?;
+                break;
+            }
+            checkpointer.tick(self, &state)?;
         }
-        let mut solutions = vec![];
-        for mut solution in solution_bag {
-            if let Some(filter) = &self.filter {
+        if status == SolveStatus::Complete {
+            checkpointer.finish()?;
+        }
+
+        let mut solutions = state
+            .solutions
+            .into_iter()
+            .map(BitArraySolution)
+            .collect_vec();
+        solutions.sort_unstable();
+        limiter.truncate(&mut solutions);
+        report.count_compositions(self, solutions.iter().map(|solution| &solution.0));
+        if let Some(filter) = &self.filter {
+            for solution in &mut solutions {
                 solution.inflate(filter);
             }
-            solutions.push(solution);
         }
-        solutions
+        report.elapsed = started.elapsed();
+        Ok(SolveOutcome {
+            solutions,
+            status,
+            report,
+        })
+    }
+
+    /// Explore the `parents` of a generation of `solve_parallel` in parallel.
+    /// Returns the solutions among them, and their children which pass the
+    /// solve `filter`. Stops early when `limiter` interrupts the solve.
+    fn expand_chunk(
+        &self,
+        parents: &[FixedBitSet],
+        filter: &mut ShardedFilter,
+        limiter: &Limiter,
+        statistics: &mut DepthStatistics,
+    ) -> (Vec<FixedBitSet>, Vec<FixedBitSet>) {
+        let expansions = parents
+            .par_iter()
+            .filter(|_| limiter.interrupted().is_none())
+            .map(|vector| {
+                let new_candidates = self.get_possibilities(vector).map(|possibilities| {
+                    possibilities
+                        .ones()
+                        .map(|fillable_site| {
+                            let mut new = vector.clone();
+                            new.set(fillable_site, true);
+                            new
+                        })
+                        .collect_vec()
+                });
+                (vector, new_candidates)
+            })
+            .collect::<Vec<_>>();
+
+        statistics.explored += expansions.len();
+        let mut solutions = vec![];
+        let mut new_candidates = vec![];
+        for (vector, expansion) in expansions {
+            match expansion {
+                Err(_) => statistics.pruned_by_possibilities += 1,
+                Ok(candidates) if candidates.is_empty() => {
+                    solutions.push(vector.clone());
+                    statistics.solutions += 1;
+                }
+                Ok(candidates) => new_candidates.extend(candidates),
+            }
+        }
+        let unfiltered = new_candidates.len();
+        let children = filter.filter(new_candidates);
+        statistics.pruned_by_filter += unfiltered - children.len();
+        (solutions, children)
     }
 
     pub fn print_distances(&self) {
-        for row in &self.distances_matrix {
+        let len = self.distances.len();
+        for one in 0..len {
+            let row = (0..len)
+                .map(|two| self.distances.distance(one, two))
+                .collect_vec();
             println!("{row:?}");
         }
     }
@@ -637,9 +1387,9 @@ impl BitArrayRepresentation {
         for (number, row) in self.exclusion_matrix.iter().enumerate() {
             output += format!("{number:5}. {row}\n").as_str();
         }
-        output += format!("  tripoint_mask = \n    {}\n", self.tripoint_mask).as_str();
-        output += format!("  midpoint_mask = \n    {}\n", self.midpoint_mask).as_str();
-        output += format!("  singlet_mask = \n    {}\n", self.singlet_mask).as_str();
+        for (kind, mask) in &self.kind_masks {
+            output += format!("  {}_mask = \n    {}\n", kind.name(), mask).as_str();
+        }
         output += "  filter = \n    ";
         output += self
             .filter
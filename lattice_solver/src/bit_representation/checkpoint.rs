@@ -0,0 +1,330 @@
+//! Periodic snapshots of a breadth-first solve, so a run which is stopped, for
+//! example by the time limit of a cluster job, can be continued later.
+use fixedbitset::FixedBitSet;
+use itertools::Itertools;
+use json::{object, JsonValue};
+use ordered_float::NotNan;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{ErrorKind, Read},
+    mem,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    close_vector_tree::CloseVectorTreeMap,
+    save::{
+        bitset_from_json, bitset_to_json, candidates_from_json, candidates_to_json, check_header,
+        floats_from_json, get_usize, header, indices_from_json, invalid, replace_file,
+    },
+    BitArrayRepresentation, BitArraySettings, LatticeError,
+};
+
+/// Where and how often `BitArrayRepresentation::solve_checkpointed` and
+/// `BitArrayRepresentation::solve_parallel_checkpointed` write their progress.
+///
+/// A solve only continues from an existing checkpoint file when `resume` is
+/// set, otherwise it starts over and replaces the file. The file is removed
+/// once the solve is done.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoints {
+    pub path: PathBuf,
+    /// The least amount of time between two checkpoints.
+    pub interval: Duration,
+    /// Whether to continue from the checkpoint file when it exists.
+    pub resume: bool,
+}
+
+impl Checkpoints {
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+            resume: false,
+        }
+    }
+
+    /// Builder method to continue from the checkpoint file when it exists.
+    #[must_use]
+    pub fn resuming(self) -> Self {
+        Self {
+            resume: true,
+            ..self
+        }
+    }
+
+    /// Remove the checkpoint file, if it exists.
+    fn clear(&self) -> Result<(), LatticeError> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            result => result.map_err(Into::into),
+        }
+    }
+}
+
+/// The solver which wrote a checkpoint. Their checkpoints can not be
+/// exchanged, as only the sequential solver checkpoints within a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Solver {
+    Sequential,
+    Parallel,
+}
+
+/// Everything needed to continue a breadth-first solve.
+pub struct SolveState {
+    pub solver: Solver,
+    pub find_all: bool,
+    /// The depth of `current_generation`, the filled sites are at depth 1.
+    pub depth: usize,
+    pub current_generation: Vec<FixedBitSet>,
+    /// The amount of candidates of `current_generation` which have been
+    /// explored.
+    pub position: usize,
+    pub next_generation: Vec<FixedBitSet>,
+    /// The solutions found so far, not yet inflated.
+    pub solutions: Vec<FixedBitSet>,
+    pub structure_map: HashMap<Vec<usize>, Vec<Vec<f32>>>,
+    pub new_structure_map: CloseVectorTreeMap,
+}
+
+impl SolveState {
+    /// Continue from the checkpoint file when it exists and should be
+    /// resumed, or start at the filled sites of `representation`.
+    pub fn start(
+        representation: &BitArrayRepresentation,
+        solver: Solver,
+        find_all: bool,
+        checkpoints: Option<&Checkpoints>,
+    ) -> Result<Self, LatticeError> {
+        if let Some(checkpoints) = checkpoints {
+            if checkpoints.resume && checkpoints.path.exists() {
+                return Self::load(representation, solver, find_all, &checkpoints.path);
+            }
+        }
+        Ok(Self {
+            solver,
+            find_all,
+            depth: 1,
+            current_generation: vec![representation.filled_sites.clone()],
+            position: 0,
+            next_generation: vec![],
+            solutions: vec![],
+            structure_map: HashMap::new(),
+            new_structure_map: CloseVectorTreeMap::new(tolerance(representation)),
+        })
+    }
+
+    /// Whether another generation has to be explored.
+    pub const fn should_continue(&self) -> bool {
+        !self.current_generation.is_empty() && (self.solutions.is_empty() || self.find_all)
+    }
+
+    /// Move on to the next generation. The filters only compare structures
+    /// within a generation, so they start empty.
+    pub fn next_depth(&mut self, representation: &BitArrayRepresentation) {
+        mem::swap(&mut self.current_generation, &mut self.next_generation);
+        self.next_generation.clear();
+        self.position = 0;
+        self.depth += 1;
+        self.structure_map.clear();
+        self.new_structure_map = CloseVectorTreeMap::new(tolerance(representation));
+    }
+}
+
+/// Writes the checkpoints of a solve, at most once per interval.
+pub struct Checkpointer<'a> {
+    checkpoints: Option<&'a Checkpoints>,
+    last: Instant,
+}
+
+impl<'a> Checkpointer<'a> {
+    pub fn new(checkpoints: Option<&'a Checkpoints>) -> Self {
+        Self {
+            checkpoints,
+            last: Instant::now(),
+        }
+    }
+
+    /// Write `state` when the interval has passed since the last checkpoint.
+    pub fn tick(
+        &mut self,
+        representation: &BitArrayRepresentation,
+        state: &SolveState,
+    ) -> Result<(), LatticeError> {
+        if let Some(checkpoints) = self.checkpoints {
+            if self.last.elapsed() >= checkpoints.interval {
+                state.save(representation, &checkpoints.path)?;
+                self.last = Instant::now();
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `state` now, regardless of the interval.
+    pub fn write(
+        &mut self,
+        representation: &BitArrayRepresentation,
+        state: &SolveState,
+    ) -> Result<(), LatticeError> {
+        if let Some(checkpoints) = self.checkpoints {
+            state.save(representation, &checkpoints.path)?;
+            self.last = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Remove the checkpoint of a finished solve.
+    pub fn finish(self) -> Result<(), LatticeError> {
+        self.checkpoints.map_or(Ok(()), Checkpoints::clear)
+    }
+}
+
+fn tolerance(representation: &BitArrayRepresentation) -> ordered_float::NotNan<f32> {
+    representation
+        .options
+        .difference_distance
+        .try_into()
+        .unwrap()
+}
+
+impl SolveState {
+    fn to_json(&self, representation: &BitArrayRepresentation) -> JsonValue {
+        let mut data = header("checkpoint");
+        data["solver"] = match self.solver {
+            Solver::Sequential => "solve",
+            Solver::Parallel => "solve_parallel",
+        }
+        .into();
+        data["find_all"] = self.find_all.into();
+        data["filled_sites"] = bitset_to_json(&representation.filled_sites);
+        data["options"] = representation.options.to_json();
+        data["depth"] = self.depth.into();
+        data["position"] = self.position.into();
+        data["current_generation"] = candidates_to_json(&self.current_generation);
+        data["next_generation"] = candidates_to_json(&self.next_generation);
+        data["solutions"] = candidates_to_json(&self.solutions);
+        data["structure_map"] = self
+            .structure_map
+            .iter()
+            .map(|(kind_counts, structures)| {
+                object! { kind_counts: kind_counts.clone(), structures: structures.clone() }
+            })
+            .collect_vec()
+            .into();
+        data["close_vectors"] = self
+            .new_structure_map
+            .entries()
+            .map(|(kind_counts, vectors)| {
+                let vectors = vectors
+                    .into_iter()
+                    .map(|vector| vector.into_iter().map(NotNan::into_inner).collect_vec())
+                    .collect_vec();
+                object! { kind_counts: kind_counts.to_vec(), vectors: vectors }
+            })
+            .collect_vec()
+            .into();
+        data
+    }
+
+    /// Deserialize a checkpoint written by `SolveState::to_json`, which has to
+    /// belong to a solve of `representation` by `solver` with `find_all`.
+    fn from_json(
+        data: &JsonValue,
+        representation: &BitArrayRepresentation,
+        solver: Solver,
+        find_all: bool,
+    ) -> Result<Self, LatticeError> {
+        check_header(data, "checkpoint")?;
+        let written_by = match data["solver"].as_str() {
+            Some("solve") => Solver::Sequential,
+            Some("solve_parallel") => Solver::Parallel,
+            _ => return Err(invalid("solver")),
+        };
+        if written_by != solver {
+            return Err(LatticeError::CheckpointMismatch("solver".into()));
+        }
+        if data["find_all"].as_bool() != Some(find_all) {
+            return Err(LatticeError::CheckpointMismatch("find_all".into()));
+        }
+        if bitset_from_json(&data["filled_sites"], "filled_sites")? != representation.filled_sites {
+            return Err(LatticeError::CheckpointMismatch("filled_sites".into()));
+        }
+        if BitArraySettings::from_json(&data["options"])? != representation.options {
+            return Err(LatticeError::CheckpointMismatch("settings".into()));
+        }
+
+        let size = representation.filled_sites.len();
+        let current_generation =
+            candidates_from_json(&data["current_generation"], size, "current_generation")?;
+        let position = get_usize(data, "position")?;
+        if position > current_generation.len() {
+            return Err(invalid("position"));
+        }
+
+        let mut structure_map = HashMap::new();
+        for entry in data["structure_map"].members() {
+            let structures = entry["structures"]
+                .members()
+                .map(|structure| floats_from_json(structure, "structure_map"))
+                .collect::<Result<Vec<_>, _>>()?;
+            structure_map.insert(
+                indices_from_json(&entry["kind_counts"], "structure_map")?,
+                structures,
+            );
+        }
+        let mut new_structure_map = CloseVectorTreeMap::new(
+            NotNan::new(representation.options.difference_distance)
+                .map_err(|_| invalid("difference_distance"))?,
+        );
+        for entry in data["close_vectors"].members() {
+            let kind_counts = indices_from_json(&entry["kind_counts"], "close_vectors")?;
+            for vector in entry["vectors"].members() {
+                let vector = floats_from_json(vector, "close_vectors")?
+                    .into_iter()
+                    .map(NotNan::new)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid("close_vectors"))?;
+                new_structure_map.insert_unchecked(kind_counts.clone(), vector);
+            }
+        }
+
+        Ok(Self {
+            solver,
+            find_all,
+            depth: get_usize(data, "depth")?,
+            current_generation,
+            position,
+            next_generation: candidates_from_json(
+                &data["next_generation"],
+                size,
+                "next_generation",
+            )?,
+            solutions: candidates_from_json(&data["solutions"], size, "solutions")?,
+            structure_map,
+            new_structure_map,
+        })
+    }
+
+    /// Write a checkpoint, replacing the previous one at `path`.
+    pub fn save(
+        &self,
+        representation: &BitArrayRepresentation,
+        path: &Path,
+    ) -> Result<(), LatticeError> {
+        replace_file(path, &self.to_json(representation))
+    }
+
+    /// Load a checkpoint written by `SolveState::save`.
+    pub fn load(
+        representation: &BitArrayRepresentation,
+        solver: Solver,
+        find_all: bool,
+        path: &Path,
+    ) -> Result<Self, LatticeError> {
+        let mut buffer = String::new();
+        File::open(path)?.read_to_string(&mut buffer)?;
+        Self::from_json(&json::parse(&buffer)?, representation, solver, find_all)
+    }
+}
@@ -0,0 +1,40 @@
+//! Solution totals, for when only the amount of structures matters.
+use std::collections::BTreeMap;
+
+use crate::SiteKind;
+
+/// The amount of solutions of every composition, counted by
+/// `BitArrayRepresentation::count_solutions`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SolutionCounts {
+    /// The kinds of sites, in the order of the compositions.
+    pub kinds: Vec<SiteKind>,
+    /// For every composition, the amount of sites of every kind, the amount
+    /// of solutions with that composition.
+    pub counts: BTreeMap<Vec<usize>, usize>,
+}
+
+impl SolutionCounts {
+    pub(crate) const fn new(kinds: Vec<SiteKind>) -> Self {
+        Self {
+            kinds,
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// The amount of solutions of all compositions together.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub(crate) fn add(&mut self, composition: Vec<usize>) {
+        *self.counts.entry(composition).or_default() += 1;
+    }
+
+    pub(crate) fn merge(mut self, other: Self) -> Self {
+        for (composition, count) in other.counts {
+            *self.counts.entry(composition).or_default() += count;
+        }
+        self
+    }
+}
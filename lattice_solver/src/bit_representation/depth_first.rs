@@ -0,0 +1,277 @@
+//! Depth-first search through the candidate structures, which yields the
+//! solutions one at a time.
+use fixedbitset::FixedBitSet;
+use std::collections::HashMap;
+
+use super::{
+    report::filter_memory,
+    site_set::{self, SiteSet},
+};
+use crate::{
+    close_vector_tree::CloseVectorTreeMap, BitArrayRepresentation, BitArraySolution,
+    DepthStatistics, SolveStatus,
+};
+
+/// The amount of structures `Search::next_candidate_checked` explores between
+/// two checks.
+const CHECK_INTERVAL: usize = 256;
+
+/// The state of a depth-first search through the structures of a
+/// `BitArrayRepresentation`.
+///
+/// Only the unexplored siblings of the current structure are kept, so memory
+/// use scales with the depth of the search. Every structure is stored as a
+/// `FixedBitSet`, regardless of the size of the lattice.
+///
+/// Use `BitArrayRepresentation::solve_iter` unless the search has to outlive a
+/// borrow of the representation.
+pub struct DepthFirstSearch(Search<FixedBitSet>);
+
+impl DepthFirstSearch {
+    /// Start a search from the filled sites of `representation`.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn new(representation: &BitArrayRepresentation) -> Self {
+        Self::starting_at(representation, representation.filled_sites.clone())
+    }
+
+    /// Start a search which only explores `candidate` and the structures
+    /// which can be made from it.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn starting_at(representation: &BitArrayRepresentation, candidate: FixedBitSet) -> Self {
+        let available = representation.matrix_vector_multiply(&candidate);
+        Self(Search::new(representation, candidate, available))
+    }
+
+    /// Skip the structures with more than `max_sites` filled sites from now
+    /// on.
+    pub const fn limit_sites(&mut self, max_sites: usize) {
+        self.0.limit_sites(max_sites);
+    }
+
+    /// The amount of structures waiting to be explored.
+    pub const fn frontier_len(&self) -> usize {
+        self.0.frontier_len()
+    }
+
+    /// What happened at every depth so far, where the structure the search
+    /// started at is at depth 1.
+    pub fn statistics(&self) -> &[DepthStatistics] {
+        self.0.statistics()
+    }
+
+    /// The most structures which were waiting to be explored at once.
+    pub const fn peak_frontier(&self) -> usize {
+        self.0.peak_frontier()
+    }
+
+    /// Continue the search until the next solution is found. Returns `None`
+    /// when all structures have been explored.
+    ///
+    /// `representation` must be the representation the search was started
+    /// with.
+    pub fn next_solution(
+        &mut self,
+        representation: &BitArrayRepresentation,
+    ) -> Option<BitArraySolution> {
+        self.0.next_candidate(representation).map(|candidate| {
+            let mut solution = BitArraySolution(candidate);
+            if let Some(filter) = &representation.filter {
+                solution.inflate(filter);
+            }
+            solution
+        })
+    }
+}
+
+/// The depth-first search behind `DepthFirstSearch`, for any kind of
+/// `SiteSet`. The solvers use the smallest kind which fits the lattice.
+pub struct Search<S> {
+    /// The structures waiting to be explored, with their available sites.
+    stack: Vec<(S, S)>,
+    structure_map: HashMap<Vec<usize>, Vec<Vec<f32>>>,
+    new_structure_map: CloseVectorTreeMap,
+    /// Structures with more sites than this are not explored.
+    max_sites: usize,
+    /// The amount of sites of the structure the search started at.
+    start_sites: usize,
+    depths: Vec<DepthStatistics>,
+    peak_frontier: usize,
+    /// The amount of structures explored so far.
+    explored: usize,
+}
+
+impl<S: SiteSet> Search<S> {
+    /// Start a search at `candidate`, of which the `available` sites are
+    /// known.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn new(representation: &BitArrayRepresentation, candidate: S, available: S) -> Self {
+        Self {
+            start_sites: candidate.count_ones(),
+            stack: vec![(candidate, available)],
+            structure_map: HashMap::new(),
+            new_structure_map: CloseVectorTreeMap::new(
+                representation
+                    .options
+                    .difference_distance
+                    .try_into()
+                    .unwrap(),
+            ),
+            max_sites: usize::MAX,
+            depths: vec![],
+            peak_frontier: 1,
+            explored: 0,
+        }
+    }
+
+    /// Start a search at the filled sites of `representation`.
+    ///
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn at_filled_sites(representation: &BitArrayRepresentation) -> Self {
+        let available = representation.matrix_vector_multiply(&representation.filled_sites);
+        Self::new(
+            representation,
+            S::from_bitset(&representation.filled_sites),
+            S::from_bitset(&available),
+        )
+    }
+
+    pub const fn limit_sites(&mut self, max_sites: usize) {
+        self.max_sites = max_sites;
+    }
+
+    pub const fn frontier_len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn statistics(&self) -> &[DepthStatistics] {
+        &self.depths
+    }
+
+    pub const fn peak_frontier(&self) -> usize {
+        self.peak_frontier
+    }
+
+    /// An estimate of the memory used by the structures stored by the solve
+    /// filter, in bytes.
+    pub fn filter_memory(&self) -> usize {
+        filter_memory(&self.structure_map, &self.new_structure_map)
+    }
+
+    /// Continue the search until the next solution is found, which is not
+    /// inflated by the filter of `representation`.
+    pub fn next_candidate(&mut self, representation: &BitArrayRepresentation) -> Option<S> {
+        self.next_candidate_checked(representation, |_| None)
+            .ok()
+            .flatten()
+    }
+
+    /// Like `next_candidate`, but calls `check` with the search before the
+    /// first and then every `CHECK_INTERVAL`th explored structure, and stops
+    /// with the status it returns, if any.
+    pub fn next_candidate_checked(
+        &mut self,
+        representation: &BitArrayRepresentation,
+        mut check: impl FnMut(&mut Self) -> Option<SolveStatus>,
+    ) -> Result<Option<S>, SolveStatus> {
+        let tables = S::tables(representation);
+        while let Some((candidate, available)) = self.stack.pop() {
+            let sites = candidate.count_ones();
+            if sites > self.max_sites {
+                continue;
+            }
+            if self.explored.is_multiple_of(CHECK_INTERVAL) {
+                if let Some(status) = check(self) {
+                    self.stack.push((candidate, available));
+                    return Err(status);
+                }
+            }
+            self.explored += 1;
+            let depth = sites - self.start_sites;
+            while self.depths.len() <= depth {
+                self.depths
+                    .push(DepthStatistics::new(self.depths.len() + 1));
+            }
+            self.depths[depth].explored += 1;
+            let Ok(possibilities) = site_set::possibilities(
+                &candidate,
+                available.clone(),
+                &tables.non_singlet_mask,
+                representation.options.max_singlets,
+            ) else {
+                self.depths[depth].pruned_by_possibilities += 1;
+                continue;
+            };
+            if possibilities.is_clear() {
+                self.depths[depth].solutions += 1;
+                return Ok(Some(candidate));
+            }
+            // The children of this candidate would have too many sites.
+            if sites >= self.max_sites {
+                continue;
+            }
+            // The children are filtered in ascending order like in `solve`,
+            // so the lowest of similar children is kept, and pushed in
+            // reverse, so the lowest site is explored first.
+            let first_child = self.stack.len();
+            for fillable_site in possibilities.ones() {
+                let mut new_candidate = candidate.clone();
+                new_candidate.insert(fillable_site);
+                if representation.solving_filter_for(
+                    &new_candidate,
+                    &mut self.structure_map,
+                    &mut self.new_structure_map,
+                ) {
+                    let new_available = tables.available_after(&available, fillable_site);
+                    self.stack.push((new_candidate, new_available));
+                } else {
+                    self.depths[depth].pruned_by_filter += 1;
+                }
+            }
+            self.stack[first_child..].reverse();
+            self.peak_frontier = self.peak_frontier.max(self.stack.len());
+        }
+        Ok(None)
+    }
+}
+
+/// An iterator over the solutions of a `BitArrayRepresentation`, created by
+/// `BitArrayRepresentation::solve_iter`.
+pub struct SolutionIter<'a> {
+    representation: &'a BitArrayRepresentation,
+    search: DepthFirstSearch,
+}
+
+impl<'a> SolutionIter<'a> {
+    pub(crate) fn new(representation: &'a BitArrayRepresentation) -> Self {
+        Self {
+            representation,
+            search: DepthFirstSearch::new(representation),
+        }
+    }
+
+    /// Skip the structures with more than `max_sites` filled sites from now
+    /// on.
+    pub const fn limit_sites(&mut self, max_sites: usize) {
+        self.search.limit_sites(max_sites);
+    }
+
+    /// The amount of structures waiting to be explored.
+    pub const fn frontier_len(&self) -> usize {
+        self.search.frontier_len()
+    }
+}
+
+impl Iterator for SolutionIter<'_> {
+    type Item = BitArraySolution;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.search.next_solution(self.representation)
+    }
+}
@@ -0,0 +1,136 @@
+//! Distances between the candidate sites of a `BitArrayRepresentation`, which
+//! the solve filters compare.
+use json::{object, JsonValue};
+
+use crate::periodic::Periodicity;
+
+/// The minimum-image distance between every two candidate sites.
+///
+/// A representation made from a `Lattice` stores the positions of its sites
+/// and computes the distances when they are needed, so its memory use grows
+/// with the amount of sites instead of with its square.
+///
+/// ```
+/// # use crystacean_rs::{test_points::small_points, BitArraySettings, Lattice, OxygenIndex};
+/// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+/// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+/// let distances = &intermediary.distances;
+/// assert_eq!(distances.len(), intermediary.exclusion_matrix().len());
+/// assert_eq!(distances.distance(0, 1), lattice.distance_between(OxygenIndex(0), OxygenIndex(1)));
+/// assert_eq!(distances.distance(1, 0), distances.distance(0, 1));
+/// # Ok::<(), crystacean_rs::LatticeError>(())
+/// ```
+#[derive(Clone)]
+pub struct SiteDistances(Distances);
+
+#[derive(Clone)]
+enum Distances {
+    Positions {
+        positions: Vec<[f32; 3]>,
+        periodicity: Periodicity,
+    },
+    /// A precomputed matrix, of which only the entries above the diagonal are
+    /// used.
+    Matrix(Vec<Vec<f32>>),
+}
+
+impl SiteDistances {
+    pub(crate) const fn from_positions(positions: Vec<[f32; 3]>, periodicity: Periodicity) -> Self {
+        Self(Distances::Positions {
+            positions,
+            periodicity,
+        })
+    }
+
+    /// Distances given as a matrix, of which the entry of row `one` and column
+    /// `two` is the distance between site `one` and site `two` when
+    /// `one < two`.
+    pub const fn from_matrix(matrix: Vec<Vec<f32>>) -> Self {
+        Self(Distances::Matrix(matrix))
+    }
+
+    /// The amount of sites.
+    pub const fn len(&self) -> usize {
+        match &self.0 {
+            Distances::Positions { positions, .. } => positions.len(),
+            Distances::Matrix(matrix) => matrix.len(),
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The distance between site `one` and site `two`.
+    pub fn distance(&self, one: usize, two: usize) -> f32 {
+        match &self.0 {
+            Distances::Positions {
+                positions,
+                periodicity,
+            } => periodicity.distance(&positions[one], &positions[two]),
+            Distances::Matrix(matrix) => matrix[one.min(two)][one.max(two)],
+        }
+    }
+
+    /// The distances between the given `sites` only, which are numbered in
+    /// the order of `sites`.
+    pub(crate) fn select(&self, sites: &[usize]) -> Self {
+        Self(match &self.0 {
+            Distances::Positions {
+                positions,
+                periodicity,
+            } => Distances::Positions {
+                positions: sites.iter().map(|&site| positions[site]).collect(),
+                periodicity: periodicity.clone(),
+            },
+            Distances::Matrix(matrix) => Distances::Matrix(
+                sites
+                    .iter()
+                    .map(|&row| sites.iter().map(|&column| matrix[row][column]).collect())
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Serialize the distances, see `BitArrayRepresentation::to_json`.
+    pub(crate) fn to_json(&self) -> JsonValue {
+        match &self.0 {
+            Distances::Positions {
+                positions,
+                periodicity,
+            } => object! {
+                positions: positions.iter().map(|position| position.to_vec()).collect::<Vec<_>>(),
+                periodicity: periodicity.to_json(),
+            },
+            Distances::Matrix(matrix) => object! { matrix: matrix.clone() },
+        }
+    }
+
+    /// Deserialize distances written by `SiteDistances::to_json`.
+    pub(crate) fn from_json(data: &JsonValue) -> Option<Self> {
+        let floats = |value: &JsonValue| {
+            value
+                .members()
+                .map(|value| value.as_f64().map(|value| value as f32))
+                .collect::<Option<Vec<_>>>()
+        };
+        if data["matrix"].is_array() {
+            return data["matrix"]
+                .members()
+                .map(floats)
+                .collect::<Option<_>>()
+                .map(Self::from_matrix);
+        }
+        let positions = data["positions"]
+            .members()
+            .map(|position| match floats(position)?[..] {
+                [x, y, z] => Some([x, y, z]),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        Some(Self::from_positions(
+            positions,
+            Periodicity::from_json(&data["periodicity"])?,
+        ))
+    }
+}
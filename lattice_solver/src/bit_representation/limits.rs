@@ -0,0 +1,191 @@
+//! Bounds on exploratory solves, and the reason a bounded solve stopped.
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{BitArraySolution, SolveReport};
+
+/// Stops a running solve from another thread. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the solves using this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Bounds on a solve, see `BitArrayRepresentation::solve_limited`. The
+/// default has no bounds.
+///
+/// ```
+/// # use crystacean_rs::{CancelToken, SolveLimits};
+/// # use std::time::Duration;
+/// let token = CancelToken::new();
+/// let limits = SolveLimits::NONE
+///     .max_solutions(1000)
+///     .time_budget(Duration::from_secs(3600))
+///     .cancel_with(token.clone());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SolveLimits {
+    /// Stop when this many solutions have been found.
+    pub max_solutions: Option<usize>,
+    /// Stop when the solve has run for this long.
+    pub time_budget: Option<Duration>,
+    /// Stop when more candidate structures than this are waiting to be
+    /// explored.
+    pub max_frontier: Option<usize>,
+    /// Stop when this token is cancelled.
+    pub cancel: Option<CancelToken>,
+}
+
+impl SolveLimits {
+    /// No bounds at all.
+    pub const NONE: Self = Self {
+        max_solutions: None,
+        time_budget: None,
+        max_frontier: None,
+        cancel: None,
+    };
+
+    pub fn max_solutions(self, value: usize) -> Self {
+        Self {
+            max_solutions: Some(value),
+            ..self
+        }
+    }
+
+    pub fn time_budget(self, value: Duration) -> Self {
+        Self {
+            time_budget: Some(value),
+            ..self
+        }
+    }
+
+    pub fn max_frontier(self, value: usize) -> Self {
+        Self {
+            max_frontier: Some(value),
+            ..self
+        }
+    }
+
+    pub fn cancel_with(self, token: CancelToken) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self
+        }
+    }
+}
+
+/// Why a solve stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveStatus {
+    /// The solve explored everything it was asked to.
+    Complete,
+    MaxSolutions,
+    TimeBudget,
+    MaxFrontier,
+    Cancelled,
+}
+
+impl SolveStatus {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::MaxSolutions => "max_solutions",
+            Self::TimeBudget => "time_budget",
+            Self::MaxFrontier => "max_frontier",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for SolveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The solutions of a bounded solve. Unless the status is `Complete`, these
+/// are the solutions found before the solve stopped.
+#[derive(Debug)]
+pub struct SolveOutcome {
+    pub solutions: Vec<BitArraySolution>,
+    pub status: SolveStatus,
+    pub report: SolveReport,
+}
+
+/// Checks the `SolveLimits` of a running solve.
+pub struct Limiter<'a> {
+    limits: &'a SolveLimits,
+    start: Instant,
+}
+
+impl<'a> Limiter<'a> {
+    pub fn new(limits: &'a SolveLimits) -> Self {
+        Self {
+            limits,
+            start: Instant::now(),
+        }
+    }
+
+    /// Whether the solve has been cancelled or has run out of time.
+    pub fn interrupted(&self) -> Option<SolveStatus> {
+        if self
+            .limits
+            .cancel
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+        {
+            return Some(SolveStatus::Cancelled);
+        }
+        if self
+            .limits
+            .time_budget
+            .is_some_and(|budget| self.start.elapsed() >= budget)
+        {
+            return Some(SolveStatus::TimeBudget);
+        }
+        None
+    }
+
+    /// The limit the solve has reached with `solutions` found and `frontier`
+    /// candidates waiting, if any.
+    pub fn check(&self, solutions: usize, frontier: usize) -> Option<SolveStatus> {
+        self.interrupted().or_else(|| {
+            if self
+                .limits
+                .max_solutions
+                .is_some_and(|max| solutions >= max)
+            {
+                Some(SolveStatus::MaxSolutions)
+            } else if self.limits.max_frontier.is_some_and(|max| frontier > max) {
+                Some(SolveStatus::MaxFrontier)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Drop the solutions beyond `max_solutions`.
+    pub fn truncate(&self, solutions: &mut Vec<BitArraySolution>) {
+        if let Some(max) = self.limits.max_solutions {
+            solutions.truncate(max);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Depth-first search through the candidate structures on all threads. The
+//! structures close to the filled sites each become a rayon task, and deeper
+//! subtrees are searched sequentially by the thread which picked them up, so
+//! idle threads steal whole subtrees from busy ones.
+use fixedbitset::FixedBitSet;
+use kdam::{Bar, BarExt};
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use super::{
+    depth_first::Search,
+    limits::Limiter,
+    site_set::{self, SiteSet},
+};
+use crate::{
+    close_vector_tree::CloseVectorTreeMap, BitArrayRepresentation, DepthStatistics, SolveReport,
+    SolveStatus,
+};
+
+/// Structures up to this depth are split into a task per child. Deeper
+/// structures are the root of a sequential depth-first search.
+const SPLIT_DEPTH: usize = 4;
+
+/// The amount of solutions between two updates of the progress bar.
+const PROGRESS_INTERVAL: usize = 64;
+
+/// The state shared by the tasks of a parallel depth-first search, which
+/// stores every structure as an `S`.
+pub struct ParallelSearch<'a, S> {
+    representation: &'a BitArrayRepresentation,
+    find_all: bool,
+    limiter: &'a Limiter<'a>,
+    /// Shows `found`, updated by whichever task gets hold of it.
+    progress: Mutex<Bar>,
+    /// The amount of solutions found by all tasks so far.
+    found: AtomicUsize,
+    /// The amount of sites of the smallest solution found by any task, which
+    /// all tasks prune against when not finding all solutions.
+    fewest_sites: AtomicUsize,
+    stop: AtomicBool,
+    /// The first limit which was reached.
+    status: Mutex<Option<SolveStatus>>,
+    /// The solutions of the finished tasks, not yet inflated.
+    solutions: Mutex<Vec<FixedBitSet>>,
+    depths: Mutex<Vec<DepthStatistics>>,
+    /// The largest frontier of a single subtree search.
+    peak_frontier: AtomicUsize,
+    /// The most memory used by the solve filter of a single subtree search.
+    peak_filter_memory: AtomicUsize,
+    set: PhantomData<S>,
+}
+
+impl<'a, S: SiteSet> ParallelSearch<'a, S> {
+    pub const fn new(
+        representation: &'a BitArrayRepresentation,
+        find_all: bool,
+        limiter: &'a Limiter<'a>,
+        progress: Bar,
+    ) -> Self {
+        Self {
+            representation,
+            find_all,
+            limiter,
+            progress: Mutex::new(progress),
+            found: AtomicUsize::new(0),
+            fewest_sites: AtomicUsize::new(usize::MAX),
+            stop: AtomicBool::new(false),
+            status: Mutex::new(None),
+            solutions: Mutex::new(vec![]),
+            depths: Mutex::new(vec![]),
+            peak_frontier: AtomicUsize::new(1),
+            peak_filter_memory: AtomicUsize::new(0),
+            set: PhantomData,
+        }
+    }
+
+    /// Search all structures which can be made from the filled sites, on the
+    /// threads of the current rayon pool.
+    pub fn run(&self) {
+        let filled_sites = &self.representation.filled_sites;
+        let available = self.representation.matrix_vector_multiply(filled_sites);
+        self.explore(S::from_bitset(filled_sites), S::from_bitset(&available), 1);
+    }
+
+    /// The solutions found and why the search stopped. The statistics of the
+    /// search are written to `report`.
+    pub fn finish(self, report: &mut SolveReport) -> (Vec<FixedBitSet>, SolveStatus) {
+        let _ = self
+            .progress
+            .into_inner()
+            .unwrap()
+            .update_to(self.found.into_inner());
+        report.depths = self.depths.into_inner().unwrap();
+        let solutions = self.solutions.into_inner().unwrap();
+        let threads = rayon::current_num_threads();
+        report.observe_candidates(
+            self.peak_frontier.into_inner() * threads + solutions.len(),
+            self.representation.filled_sites.len(),
+            self.peak_filter_memory.into_inner() * threads,
+        );
+        let status = self
+            .status
+            .into_inner()
+            .unwrap()
+            .unwrap_or(SolveStatus::Complete);
+        (solutions, status)
+    }
+
+    /// Explore `candidate`, of which the `available` sites are known.
+    fn explore(&self, candidate: S, available: S, depth: usize) {
+        if let Some(reached) = self.limiter.interrupted() {
+            self.stop_with(reached);
+        }
+        if self.stop.load(Ordering::Relaxed) {
+            return;
+        }
+        if !self.find_all && candidate.count_ones() > self.fewest_sites.load(Ordering::Relaxed) {
+            return;
+        }
+        if depth > SPLIT_DEPTH {
+            self.search_subtree(candidate, available, depth);
+            return;
+        }
+
+        let mut statistics = DepthStatistics::new(depth);
+        statistics.explored = 1;
+        let tables = S::tables(self.representation);
+        let Ok(possibilities) = site_set::possibilities(
+            &candidate,
+            available.clone(),
+            &tables.non_singlet_mask,
+            self.representation.options.max_singlets,
+        ) else {
+            statistics.pruned_by_possibilities = 1;
+            self.add_statistics(depth, &[statistics]);
+            return;
+        };
+        if possibilities.is_clear() {
+            statistics.solutions = 1;
+            self.add_statistics(depth, &[statistics]);
+            if !self.find_all {
+                self.fewest_sites
+                    .fetch_min(candidate.count_ones(), Ordering::Relaxed);
+            }
+            self.add_solutions(&[candidate]);
+            self.solution_found(0);
+            return;
+        }
+
+        // Only the siblings are compared by the solve filter, as the
+        // subtrees are searched independently.
+        let mut structure_map = HashMap::new();
+        let mut new_structure_map = CloseVectorTreeMap::new(
+            self.representation
+                .options
+                .difference_distance
+                .try_into()
+                .unwrap(),
+        );
+        let mut children = vec![];
+        for fillable_site in possibilities.ones() {
+            let mut child = candidate.clone();
+            child.insert(fillable_site);
+            if self.representation.solving_filter_for(
+                &child,
+                &mut structure_map,
+                &mut new_structure_map,
+            ) {
+                let child_available = tables.available_after(&available, fillable_site);
+                children.push((child, child_available));
+            } else {
+                statistics.pruned_by_filter += 1;
+            }
+        }
+        self.add_statistics(depth, &[statistics]);
+        children
+            .into_par_iter()
+            .for_each(|(child, child_available)| self.explore(child, child_available, depth + 1));
+    }
+
+    /// Search the subtree of `candidate` on this thread.
+    fn search_subtree(&self, candidate: S, available: S, depth: usize) {
+        let mut search = Search::new(self.representation, candidate, available);
+        let mut found: Vec<S> = vec![];
+        loop {
+            let next = search.next_candidate_checked(self.representation, |search| {
+                if !self.find_all {
+                    search.limit_sites(self.fewest_sites.load(Ordering::Relaxed));
+                }
+                self.check(search.frontier_len())
+            });
+            let solution = match next {
+                Ok(Some(solution)) => solution,
+                Ok(None) => break,
+                Err(reached) => {
+                    self.stop_with(reached);
+                    break;
+                }
+            };
+            if !self.find_all {
+                let sites = solution.count_ones();
+                let fewest_sites = self
+                    .fewest_sites
+                    .fetch_min(sites, Ordering::Relaxed)
+                    .min(sites);
+                search.limit_sites(fewest_sites);
+                if sites > fewest_sites {
+                    continue;
+                }
+                found.retain(|found| found.count_ones() == fewest_sites);
+            }
+            found.push(solution);
+            self.solution_found(search.frontier_len());
+            if self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+        self.peak_frontier
+            .fetch_max(search.peak_frontier(), Ordering::Relaxed);
+        self.peak_filter_memory
+            .fetch_max(search.filter_memory(), Ordering::Relaxed);
+        self.add_statistics(depth, search.statistics());
+        self.add_solutions(&found);
+    }
+
+    /// Add the statistics of a task, where the first of `depths` is at
+    /// `first_depth`.
+    fn add_statistics(&self, first_depth: usize, depths: &[DepthStatistics]) {
+        let mut all_depths = self.depths.lock().unwrap();
+        for (depth, statistics) in (first_depth..).zip(depths) {
+            while all_depths.len() < depth {
+                let next = all_depths.len() + 1;
+                all_depths.push(DepthStatistics::new(next));
+            }
+            all_depths[depth - 1].add(statistics);
+        }
+    }
+
+    fn add_solutions(&self, solutions: &[S]) {
+        if !solutions.is_empty() {
+            let len = self.representation.filled_sites.len();
+            self.solutions.lock().unwrap().extend(
+                solutions
+                    .iter()
+                    .map(|solution| solution.to_bitset(len).into_owned()),
+            );
+        }
+    }
+
+    /// Count a solution, found while `frontier` structures of its subtree are
+    /// waiting, and stop when a limit is reached. The progress bar is updated
+    /// once every `PROGRESS_INTERVAL` solutions, unless another task is
+    /// updating it.
+    fn solution_found(&self, frontier: usize) {
+        let total = self.found.fetch_add(1, Ordering::Relaxed) + 1;
+        if total.is_multiple_of(PROGRESS_INTERVAL) {
+            if let Ok(mut progress) = self.progress.try_lock() {
+                let _ = progress.update_to(total);
+            }
+        }
+        if let Some(reached) = self.limiter.check(total, frontier) {
+            self.stop_with(reached);
+        }
+    }
+
+    /// The limit a subtree search with `frontier` structures waiting has
+    /// reached, or the one which stopped another task.
+    fn check(&self, frontier: usize) -> Option<SolveStatus> {
+        if self.stop.load(Ordering::Relaxed) {
+            return *self.status.lock().unwrap();
+        }
+        self.limiter
+            .check(self.found.load(Ordering::Relaxed), frontier)
+    }
+
+    /// Stop all tasks, remembering the first limit which was reached.
+    fn stop_with(&self, status: SolveStatus) {
+        self.status.lock().unwrap().get_or_insert(status);
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
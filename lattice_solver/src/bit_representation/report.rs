@@ -0,0 +1,176 @@
+//! Statistics of a solve, to compare solve filters and tolerances.
+use fixedbitset::FixedBitSet;
+use std::{collections::HashMap, fmt, mem, time::Duration};
+
+use crate::{
+    close_vector_tree::CloseVectorTreeMap, BitArrayRepresentation, BitArraySettings, SiteKind,
+    SolutionCounts,
+};
+
+/// What happened at one depth of a solve. The depth of the filled sites is 1,
+/// and every depth adds one site.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepthStatistics {
+    pub depth: usize,
+    /// The amount of candidate structures explored at this depth.
+    pub explored: usize,
+    /// Explored structures which can not lead to a solution, according to
+    /// `get_possibilities`.
+    pub pruned_by_possibilities: usize,
+    /// Structures of the next depth removed by the solve filter.
+    pub pruned_by_filter: usize,
+    pub solutions: usize,
+    /// Time spent on this depth. Not measured when solving depth-first, as
+    /// the depths are explored interleaved.
+    pub elapsed: Duration,
+}
+
+impl DepthStatistics {
+    pub(crate) fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            ..Self::default()
+        }
+    }
+
+    /// Add the counts of `other`, of the same depth, to these.
+    pub(crate) fn add(&mut self, other: &Self) {
+        self.explored += other.explored;
+        self.pruned_by_possibilities += other.pruned_by_possibilities;
+        self.pruned_by_filter += other.pruned_by_filter;
+        self.solutions += other.solutions;
+        self.elapsed += other.elapsed;
+    }
+}
+
+/// Statistics of a solve, returned in its `SolveOutcome`. A solve resumed from
+/// a checkpoint only reports what happened after resuming.
+///
+/// ```
+/// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice, SolveLimits};
+/// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+/// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+/// let outcome = intermediary.solve_limited(true, true, &SolveLimits::NONE);
+/// let report = outcome.report;
+/// assert_eq!(report.compositions.total(), outcome.solutions.len());
+/// assert_eq!(report.depths[0].explored, 1);
+/// assert!(report.to_string().contains("solutions"));
+/// # Ok::<(), crystacean_rs::LatticeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolveReport {
+    pub settings: BitArraySettings,
+    pub depths: Vec<DepthStatistics>,
+    /// The amount of solutions of every composition.
+    pub compositions: SolutionCounts,
+    /// The most candidate structures and solutions held at once.
+    pub peak_candidates: usize,
+    /// An estimate of the most memory used at once by the candidate
+    /// structures, the solutions and the structures stored by the solve
+    /// filter, in bytes.
+    pub peak_memory: usize,
+    pub elapsed: Duration,
+}
+
+impl SolveReport {
+    pub(crate) fn new(representation: &BitArrayRepresentation) -> Self {
+        Self {
+            settings: representation.options,
+            depths: vec![],
+            compositions: SolutionCounts::new(
+                representation
+                    .kind_masks
+                    .iter()
+                    .map(|(kind, _)| kind.clone())
+                    .collect(),
+            ),
+            peak_candidates: 0,
+            peak_memory: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Take note of `candidates` structures of `sites` sites being held at
+    /// once, next to `filter_memory` bytes stored by the solve filter.
+    pub const fn observe_candidates(
+        &mut self,
+        candidates: usize,
+        sites: usize,
+        filter_memory: usize,
+    ) {
+        let blocks = sites.div_ceil(usize::BITS as usize);
+        let memory = candidates
+            * (mem::size_of::<FixedBitSet>() + blocks * mem::size_of::<usize>())
+            + filter_memory;
+        if candidates > self.peak_candidates {
+            self.peak_candidates = candidates;
+        }
+        if memory > self.peak_memory {
+            self.peak_memory = memory;
+        }
+    }
+
+    /// Count the compositions of the uninflated `solutions`.
+    pub(crate) fn count_compositions<'a>(
+        &mut self,
+        representation: &BitArrayRepresentation,
+        solutions: impl IntoIterator<Item = &'a FixedBitSet>,
+    ) {
+        for solution in solutions {
+            self.compositions.add(representation.kind_counts(solution));
+        }
+    }
+}
+
+impl fmt::Display for SolveReport {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?}", self.settings)?;
+        writeln!(
+            f,
+            "{:>6} {:>12} {:>12} {:>12} {:>10} {:>10}",
+            "depth", "explored", "impossible", "filtered", "solutions", "seconds"
+        )?;
+        for depth in &self.depths {
+            writeln!(
+                f,
+                "{:>6} {:>12} {:>12} {:>12} {:>10} {:>10.3}",
+                depth.depth,
+                depth.explored,
+                depth.pruned_by_possibilities,
+                depth.pruned_by_filter,
+                depth.solutions,
+                depth.elapsed.as_secs_f64()
+            )?;
+        }
+        let kinds = self
+            .compositions
+            .kinds
+            .iter()
+            .map(SiteKind::name)
+            .collect::<Vec<_>>();
+        writeln!(f, "solutions per amount of {}:", kinds.join(", "))?;
+        for (composition, count) in &self.compositions.counts {
+            writeln!(f, "  {composition:?}: {count}")?;
+        }
+        write!(
+            f,
+            "peak of {} candidates, ~{:.1} MiB, in {:.3} s",
+            self.peak_candidates,
+            self.peak_memory as f64 / 1024.0 / 1024.0,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// An estimate of the memory used by the structures a solve filter stored in
+/// `structure_map` and `new_structure_map`, in bytes.
+pub fn filter_memory(
+    structure_map: &HashMap<Vec<usize>, Vec<Vec<f32>>>,
+    new_structure_map: &CloseVectorTreeMap,
+) -> usize {
+    let values: usize = structure_map.values().flatten().map(Vec::len).sum();
+    // Every value of a close vector tree is stored with the id of its vector.
+    values * mem::size_of::<f32>()
+        + new_structure_map.stored_values() * (mem::size_of::<f32>() + mem::size_of::<usize>())
+}
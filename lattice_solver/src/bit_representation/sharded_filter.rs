@@ -0,0 +1,107 @@
+//! The solve filter of `BitArrayRepresentation::solve_parallel`, which filters
+//! the structures of every composition in parallel.
+use fixedbitset::FixedBitSet;
+use itertools::Itertools;
+use ordered_float::NotNan;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use super::report::filter_memory;
+use crate::{close_vector_tree::CloseVectorTreeMap, BitArrayFilter, BitArrayRepresentation};
+
+/// The structures the solve filter kept of a single composition.
+struct Shard {
+    structure_map: HashMap<Vec<usize>, Vec<Vec<f32>>>,
+    new_structure_map: CloseVectorTreeMap,
+}
+
+/// The solve filter of a generation. The filters only compare structures with
+/// the same amount of sites of every kind, so every composition is filtered on
+/// its own, on one thread. At most as many threads as there are compositions
+/// are at work.
+pub struct ShardedFilter<'a> {
+    representation: &'a BitArrayRepresentation,
+    tolerance: NotNan<f32>,
+    shards: HashMap<Vec<usize>, Shard>,
+}
+
+impl<'a> ShardedFilter<'a> {
+    /// # Panics
+    /// Will panic when `difference_distance` is NaN.
+    pub fn new(representation: &'a BitArrayRepresentation) -> Self {
+        Self {
+            representation,
+            tolerance: representation
+                .options
+                .difference_distance
+                .try_into()
+                .unwrap(),
+            shards: HashMap::new(),
+        }
+    }
+
+    /// Keep the `candidates` which pass the filter, in their order. Giving
+    /// the structures of a generation in the order `solve` explores them, in
+    /// one or more calls, keeps the same structures as `solve` does.
+    pub fn filter(&mut self, candidates: Vec<FixedBitSet>) -> Vec<FixedBitSet> {
+        if self.representation.options.solve_filter == BitArrayFilter::None {
+            return candidates;
+        }
+        let representation = self.representation;
+        let compositions = candidates
+            .par_iter()
+            .map(|candidate| representation.kind_counts(candidate))
+            .collect::<Vec<_>>();
+        let mut indices: HashMap<Vec<usize>, Vec<usize>> = HashMap::new();
+        for (index, composition) in compositions.into_iter().enumerate() {
+            indices.entry(composition).or_default().push(index);
+        }
+        let work = indices
+            .into_iter()
+            .map(|(composition, indices)| {
+                let shard = self.shards.remove(&composition).unwrap_or_else(|| Shard {
+                    structure_map: HashMap::new(),
+                    new_structure_map: CloseVectorTreeMap::new(self.tolerance),
+                });
+                (composition, shard, indices)
+            })
+            .collect_vec();
+
+        let filtered = work
+            .into_par_iter()
+            .map(|(composition, mut shard, indices)| {
+                let kept = indices
+                    .into_iter()
+                    .filter(|&index| {
+                        representation.solving_filter(
+                            &candidates[index],
+                            &mut shard.structure_map,
+                            &mut shard.new_structure_map,
+                        )
+                    })
+                    .collect_vec();
+                (composition, shard, kept)
+            })
+            .collect::<Vec<_>>();
+
+        let mut keep = FixedBitSet::with_capacity(candidates.len());
+        for (composition, shard, kept) in filtered {
+            keep.extend(kept);
+            self.shards.insert(composition, shard);
+        }
+        candidates
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, candidate)| keep.contains(index).then_some(candidate))
+            .collect()
+    }
+
+    /// An estimate of the memory used by the structures the filter kept, in
+    /// bytes.
+    pub fn memory(&self) -> usize {
+        self.shards
+            .values()
+            .map(|shard| filter_memory(&shard.structure_map, &shard.new_structure_map))
+            .sum()
+    }
+}
@@ -0,0 +1,418 @@
+//! Sets of sites for the depth-first searches. Every explored structure is a
+//! set of sites, so lattices with few candidate sites use an `InlineBitSet`,
+//! which is copied instead of allocated, and large lattices keep using
+//! `FixedBitSet`.
+//!
+//! Only `solve_depth_first`, `solve_depth_first_parallel` and
+//! `count_solutions` pick the set with `with_site_set!`. `DepthFirstSearch`
+//! and `solve_iter` are not generic, and the generations and checkpoints of
+//! the breadth-first solves hold `FixedBitSet`s, so these always use
+//! `FixedBitSet`.
+use fixedbitset::FixedBitSet;
+use std::{borrow::Cow, sync::OnceLock};
+
+use crate::{BitArrayRepresentation, SiteKind};
+
+/// The operations the depth-first searches need on a set of sites.
+pub trait SiteSet: Clone + Send + Sync + Sized {
+    fn from_bitset(bitset: &FixedBitSet) -> Self;
+
+    /// The set as a `FixedBitSet` of `len` sites.
+    fn to_bitset(&self, len: usize) -> Cow<'_, FixedBitSet>;
+
+    fn insert(&mut self, site: usize);
+
+    fn difference_with(&mut self, other: &Self);
+
+    fn intersect_with(&mut self, other: &Self);
+
+    fn count_ones(&self) -> usize;
+
+    fn intersection_count(&self, other: &Self) -> usize;
+
+    fn is_clear(&self) -> bool;
+
+    /// The highest site in the set.
+    fn maximum(&self) -> Option<usize>;
+
+    /// Remove the sites lower than `site`.
+    fn clear_below(&mut self, site: usize);
+
+    fn ones(&self) -> impl DoubleEndedIterator<Item = usize> + '_;
+
+    /// The exclusion matrix and masks of `representation` as sets of this
+    /// kind, built on first use.
+    fn tables(representation: &BitArrayRepresentation) -> &SiteTables<Self>;
+}
+
+/// The parts of a `BitArrayRepresentation` the searches use for every
+/// structure.
+pub struct SiteTables<S> {
+    pub exclusion_matrix: Vec<S>,
+    pub non_singlet_mask: S,
+    /// The sites of every kind, in the order of `kind_masks`.
+    pub kind_masks: Vec<S>,
+}
+
+impl<S: SiteSet> SiteTables<S> {
+    fn new(representation: &BitArrayRepresentation) -> Self {
+        Self {
+            exclusion_matrix: representation
+                .exclusion_matrix
+                .iter()
+                .map(S::from_bitset)
+                .collect(),
+            non_singlet_mask: S::from_bitset(representation.non_singlet_mask()),
+            kind_masks: representation
+                .kind_masks
+                .iter()
+                .map(|(_, mask)| S::from_bitset(mask))
+                .collect(),
+        }
+    }
+
+    /// See `BitArrayRepresentation::available_after`.
+    pub fn available_after(&self, available: &S, site: usize) -> S {
+        let mut available = available.clone();
+        available.difference_with(&self.exclusion_matrix[site]);
+        available
+    }
+
+    /// See `BitArrayRepresentation::kind_counts`.
+    pub fn kind_counts(&self, vector: &S) -> Vec<usize> {
+        self.kind_masks
+            .iter()
+            .map(|mask| vector.intersection_count(mask))
+            .collect()
+    }
+}
+
+/// The tables of a `BitArrayRepresentation` for every kind of `SiteSet`.
+/// They are built from the exclusion matrix and kind masks of the
+/// representation, which can't change after construction, so the tables the
+/// searches and `count_solutions` read always match them. A representation
+/// made from other parts, as by `filtered`, gets a new, empty cache.
+#[derive(Default)]
+pub struct SiteTablesCache {
+    non_singlet_mask: OnceLock<FixedBitSet>,
+    fixed: OnceLock<SiteTables<FixedBitSet>>,
+    words_2: OnceLock<SiteTables<InlineBitSet<2>>>,
+    words_4: OnceLock<SiteTables<InlineBitSet<4>>>,
+    words_8: OnceLock<SiteTables<InlineBitSet<8>>>,
+}
+
+impl SiteTablesCache {
+    pub const fn new() -> Self {
+        Self {
+            non_singlet_mask: OnceLock::new(),
+            fixed: OnceLock::new(),
+            words_2: OnceLock::new(),
+            words_4: OnceLock::new(),
+            words_8: OnceLock::new(),
+        }
+    }
+}
+
+impl BitArrayRepresentation {
+    /// The sites of every kind except singlets, which are only used when no
+    /// other site is available.
+    pub(crate) fn non_singlet_mask(&self) -> &FixedBitSet {
+        self.site_tables.non_singlet_mask.get_or_init(|| {
+            let mut mask = FixedBitSet::with_capacity(self.filled_sites.len());
+            for (_, kind_mask) in self
+                .kind_masks
+                .iter()
+                .filter(|(kind, _)| *kind != SiteKind::Singlet)
+            {
+                mask.union_with(kind_mask);
+            }
+            mask
+        })
+    }
+}
+
+/// The possible sites to fill next in `vector`, of which the `available`
+/// sites are known, see `BitArrayRepresentation::get_possibilities`.
+pub fn possibilities<S: SiteSet>(
+    vector: &S,
+    available: S,
+    non_singlet_mask: &S,
+    max_singlets: usize,
+) -> Result<S, &'static str> {
+    let rightmost_bit = vector.maximum();
+
+    // If no possibilities found here, vector is solution
+    let mut possibilities = available;
+
+    // possible_sites can be reused later
+    let possible_sites = possibilities.count_ones();
+    if possible_sites == 0 {
+        return Ok(possibilities);
+    }
+
+    // Mask of bits covered in other threads. If a vector is empty after this, all
+    // possible following states of this vector will be covered by other threads.
+    if let Some(mask) = rightmost_bit {
+        possibilities.clear_below(mask);
+    }
+    if possibilities.is_clear() {
+        return Err("Vector is invalid.");
+    }
+
+    let mut masked_possibilities = possibilities.clone();
+    masked_possibilities.intersect_with(non_singlet_mask);
+    if masked_possibilities.is_clear() {
+        if possible_sites <= max_singlets {
+            Ok(possibilities)
+        } else {
+            Err("Vector is invalid.")
+        }
+    } else {
+        Ok(masked_possibilities)
+    }
+}
+
+/// Call `$body` with `$set` the type of the smallest `SiteSet` which holds
+/// all sites of `$representation`. See the module documentation for the
+/// solvers which use it.
+macro_rules! with_site_set {
+    ($representation:expr, $set:ident => $body:expr) => {
+        match $representation.filled_sites.len() {
+            0..=128 => {
+                type $set = $crate::bit_representation::site_set::InlineBitSet<2>;
+                $body
+            }
+            129..=256 => {
+                type $set = $crate::bit_representation::site_set::InlineBitSet<4>;
+                $body
+            }
+            257..=512 => {
+                type $set = $crate::bit_representation::site_set::InlineBitSet<8>;
+                $body
+            }
+            _ => {
+                type $set = ::fixedbitset::FixedBitSet;
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_site_set;
+
+impl SiteSet for FixedBitSet {
+    fn from_bitset(bitset: &FixedBitSet) -> Self {
+        bitset.clone()
+    }
+
+    fn to_bitset(&self, _len: usize) -> Cow<'_, FixedBitSet> {
+        Cow::Borrowed(self)
+    }
+
+    fn insert(&mut self, site: usize) {
+        self.insert(site);
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        self.difference_with(other);
+    }
+
+    fn intersect_with(&mut self, other: &Self) {
+        self.intersect_with(other);
+    }
+
+    fn count_ones(&self) -> usize {
+        self.count_ones(..)
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.intersection_count(other)
+    }
+
+    fn is_clear(&self) -> bool {
+        self.is_clear()
+    }
+
+    fn maximum(&self) -> Option<usize> {
+        self.maximum()
+    }
+
+    fn clear_below(&mut self, site: usize) {
+        self.set_range(..site, false);
+    }
+
+    fn ones(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.ones()
+    }
+
+    fn tables(representation: &BitArrayRepresentation) -> &SiteTables<Self> {
+        representation
+            .site_tables
+            .fixed
+            .get_or_init(|| SiteTables::new(representation))
+    }
+}
+
+/// A set of at most `64 * N` sites, stored without an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlineBitSet<const N: usize>([u64; N]);
+
+impl<const N: usize> InlineBitSet<N> {
+    fn from_bitset(bitset: &FixedBitSet) -> Self {
+        assert!(bitset.len() <= 64 * N, "too many sites for an inline set");
+        let mut set = Self([0; N]);
+        for site in bitset.ones() {
+            set.insert(site);
+        }
+        set
+    }
+
+    fn to_bitset(self, len: usize) -> FixedBitSet {
+        let mut bitset = FixedBitSet::with_capacity(len);
+        for site in self.ones() {
+            bitset.insert(site);
+        }
+        bitset
+    }
+
+    const fn insert(&mut self, site: usize) {
+        self.0[site / 64] |= 1_u64 << (site % 64);
+    }
+
+    fn difference_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word &= !other;
+        }
+    }
+
+    fn intersect_with(&mut self, other: &Self) {
+        for (word, other) in self.0.iter_mut().zip(other.0) {
+            *word &= other;
+        }
+    }
+
+    fn count_ones(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    fn intersection_count(&self, other: &Self) -> usize {
+        self.0
+            .iter()
+            .zip(other.0)
+            .map(|(word, other)| (word & other).count_ones() as usize)
+            .sum()
+    }
+
+    fn is_clear(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    fn maximum(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, word)| **word != 0)
+            .map(|(index, word)| 64 * index + 63 - word.leading_zeros() as usize)
+    }
+
+    fn clear_below(&mut self, site: usize) {
+        let (full_words, bits) = (site / 64, site % 64);
+        for word in &mut self.0[..full_words.min(N)] {
+            *word = 0;
+        }
+        if full_words < N {
+            self.0[full_words] &= !((1_u64 << bits) - 1);
+        }
+    }
+
+    fn ones(self) -> impl DoubleEndedIterator<Item = usize> {
+        self.0
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, word)| Bits(word).map(move |bit| 64 * index + bit))
+    }
+}
+
+/// The set bits of a word, from either end.
+struct Bits(u64);
+
+impl Iterator for Bits {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        (self.0 != 0).then(|| {
+            let bit = self.0.trailing_zeros() as usize;
+            self.0 &= self.0 - 1;
+            bit
+        })
+    }
+}
+
+impl DoubleEndedIterator for Bits {
+    fn next_back(&mut self) -> Option<usize> {
+        (self.0 != 0).then(|| {
+            let bit = 63 - self.0.leading_zeros() as usize;
+            self.0 &= !(1_u64 << bit);
+            bit
+        })
+    }
+}
+
+macro_rules! impl_inline_site_set {
+    ($($words:literal => $field:ident),*) => {$(
+        impl SiteSet for InlineBitSet<$words> {
+            fn from_bitset(bitset: &FixedBitSet) -> Self {
+                Self::from_bitset(bitset)
+            }
+
+            fn to_bitset(&self, len: usize) -> Cow<'_, FixedBitSet> {
+                Cow::Owned((*self).to_bitset(len))
+            }
+
+            fn insert(&mut self, site: usize) {
+                self.insert(site);
+            }
+
+            fn difference_with(&mut self, other: &Self) {
+                self.difference_with(other);
+            }
+
+            fn intersect_with(&mut self, other: &Self) {
+                self.intersect_with(other);
+            }
+
+            fn count_ones(&self) -> usize {
+                self.count_ones()
+            }
+
+            fn intersection_count(&self, other: &Self) -> usize {
+                self.intersection_count(other)
+            }
+
+            fn is_clear(&self) -> bool {
+                self.is_clear()
+            }
+
+            fn maximum(&self) -> Option<usize> {
+                self.maximum()
+            }
+
+            fn clear_below(&mut self, site: usize) {
+                self.clear_below(site);
+            }
+
+            fn ones(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+                (*self).ones()
+            }
+
+            fn tables(representation: &BitArrayRepresentation) -> &SiteTables<Self> {
+                representation
+                    .site_tables
+                    .$field
+                    .get_or_init(|| SiteTables::new(representation))
+            }
+        }
+    )*};
+}
+
+impl_inline_site_set!(2 => words_2, 4 => words_4, 8 => words_8);
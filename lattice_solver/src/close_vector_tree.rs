@@ -17,7 +17,7 @@ pub struct CloseVectorTree {
 }
 
 impl CloseVectorTree {
-    fn pop_size(&mut self) -> usize {
+    const fn pop_size(&mut self) -> usize {
         let answer = self.size;
         self.size += 1;
         answer
@@ -110,6 +110,19 @@ impl CloseVectorTree {
             false
         }
     }
+
+    /// The inserted vectors, in the order they were inserted.
+    pub fn vectors(&self) -> Vec<Vec<NotNan<f32>>> {
+        let mut vectors = vec![vec![NotNan::default(); self.vector.len()]; self.size];
+        for (position, tree) in self.vector.iter().enumerate() {
+            for (value, ids) in tree {
+                for &id in ids {
+                    vectors[id][position] = *value;
+                }
+            }
+        }
+        vectors
+    }
 }
 
 #[derive(fmt_derive::Debug)]
@@ -153,21 +166,37 @@ impl CloseVectorTreeMap {
         let tree = self.get_mut(point_type_count, vector.len());
         tree.insert(vector)
     }
+
+    /// The inserted vectors of every amount of sites per kind.
+    pub fn entries(&self) -> impl Iterator<Item = (&[usize], Vec<Vec<NotNan<f32>>>)> {
+        self.map
+            .iter()
+            .map(|(point_type_count, tree)| (point_type_count.0.as_slice(), tree.vectors()))
+    }
+
+    /// The amount of values of all inserted vectors together.
+    pub fn stored_values(&self) -> usize {
+        self.map
+            .values()
+            .map(|tree| tree.size * tree.vector.len())
+            .sum()
+    }
+
+    /// Insert `vector` of a structure with `kind_counts` sites per kind,
+    /// without checking for close vectors. Used to restore the vectors
+    /// returned by `entries`.
+    pub fn insert_unchecked(&mut self, kind_counts: Vec<usize>, vector: Vec<NotNan<f32>>) {
+        self.get_mut(PointTypeCount(kind_counts), vector.len())
+            .insert_blind(vector);
+    }
 }
 
+/// The amount of sites of every kind in a vector.
 #[derive(Hash, PartialEq, Eq, Debug, Default)]
-pub struct PointTypeCount {
-    tri: usize,
-    mid: usize,
-    sin: usize,
-}
+pub struct PointTypeCount(Vec<usize>);
 
 impl PointTypeCount {
     fn create(bitvector: &FixedBitSet, repr: &BitArrayRepresentation) -> Self {
-        Self {
-            tri: bitvector.union_count(&repr.tripoint_mask),
-            mid: bitvector.union_count(&repr.midpoint_mask),
-            sin: bitvector.union_count(&repr.singlet_mask),
-        }
+        Self(repr.kind_counts(bitvector))
     }
 }
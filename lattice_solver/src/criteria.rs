@@ -0,0 +1,243 @@
+//! Acceptance rules for the candidate sites which bridge several attachment
+//! points.
+//!
+//! Every group of attachment points within the search distance of each other
+//! is offered to the generators, but a nearly collinear triangle or a
+//! stretched pair relaxes badly. A `SiteCriteria` rejects such groups before a
+//! site is placed on them, and the `Lattice` keeps every rejected group
+//! together with the reason, see `Lattice::rejected_sites`.
+//!
+//! The criteria also keep filled sites apart: sites closer than
+//! `min_site_distance`, or with atoms closer than `min_crown_distance`,
+//! exclude each other.
+use std::fmt;
+
+use crate::{vector::*, LatticeIndex, SiteKind};
+
+/// An inclusive range of accepted values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Window {
+    /// Accepts every value.
+    pub const ANY: Self = Self {
+        min: f32::NEG_INFINITY,
+        max: f32::INFINITY,
+    };
+
+    pub const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::ANY
+    }
+}
+
+/// The rules a group of attachment points has to satisfy before a midpoint or
+/// tripoint is placed on it. The default accepts every group.
+///
+/// ```
+/// use crystacean_rs::criteria::{Rejection, SiteCriteria, Window};
+/// use crystacean_rs::{test_points::small_points, Lattice, SiteKind};
+///
+/// let criteria = SiteCriteria {
+///     tripoint_angle: Window::new(50.0, 70.0),
+///     ..SiteCriteria::default()
+/// };
+/// let lattice = Lattice::python_new(small_points(), 1.1, true)?.with_criteria(criteria);
+/// // The triangles of the hexagonal lattice are equilateral.
+/// assert!(lattice.rejected_sites().is_empty());
+///
+/// let criteria = SiteCriteria {
+///     midpoint_distance: Window::new(0.0, 2.0),
+///     ..SiteCriteria::default()
+/// };
+/// let lattice = lattice.with_criteria(criteria);
+/// assert_eq!(lattice.midpoints_to_plot().0.len(), 0);
+/// assert!(lattice
+///     .rejected_sites()
+///     .iter()
+///     .all(|site| site.kind == SiteKind::Midpoint && matches!(site.reason, Rejection::Distance(_))));
+///
+/// // Singlets on neighbouring points, 1.5 Å apart, can no longer be filled
+/// // together, while singlets further apart still can.
+/// let criteria = SiteCriteria {
+///     min_site_distance: 2.0,
+///     ..SiteCriteria::default()
+/// };
+/// let lattice = Lattice::from_basis_vectors(2, 2, [1.5, 0.0, 0.0], [0.75, 1.299038105676658, 0.0])?
+///     .with_criteria(criteria);
+/// let singlets = lattice.sites().filter(|s| s.kind == SiteKind::Singlet).collect::<Vec<_>>();
+/// for singlet in &singlets {
+///     for other in &singlets {
+///         let close = lattice.distance_between(singlet.index, other.index) < 2.0;
+///         assert_eq!(singlet.exclusions.contains(&other.index), close);
+///     }
+/// }
+/// # Ok::<(), crystacean_rs::LatticeError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SiteCriteria {
+    /// Accepted distance between the two points of a midpoint, in Å.
+    pub midpoint_distance: Window,
+    /// Accepted distance between every two points of a tripoint, in Å.
+    pub tripoint_distance: Window,
+    /// Accepted inner angles of the triangle of a tripoint, in degrees.
+    pub tripoint_angle: Window,
+    /// The largest accepted in-plane strain of a tripoint: the relative
+    /// difference between a side of the triangle, projected onto the plane
+    /// normal to the site, and the mean side. Use `f32::INFINITY` to accept
+    /// every triangle.
+    pub max_strain: f32,
+    /// Sites closer than this distance exclude each other, in Å. Sites of
+    /// different kinds at the same position always exclude each other.
+    pub min_site_distance: f32,
+    /// Sites of which any two atoms, including the crown atoms, are closer
+    /// than this distance exclude each other, in Å. Crown atoms within the
+    /// `merge_distance` of the `SiteGeometry` are merged into a bridge on
+    /// export, and do not exclude each other. Zero disables the check.
+    ///
+    /// ```
+    /// use crystacean_rs::criteria::SiteCriteria;
+    /// use crystacean_rs::geometry::{CrownArm, SiteGeometry};
+    /// use crystacean_rs::{Lattice, SiteKind};
+    ///
+    /// // Singlets 3 Å apart along x, with crown atoms 1.45 Å to either side,
+    /// // so the crown atoms between them are 0.1 Å apart and form a bridge.
+    /// let mut geometry = SiteGeometry::default();
+    /// geometry.singlet.crown = vec![CrownArm::new(1.45, 0.0); 2];
+    /// let criteria = SiteCriteria {
+    ///     min_crown_distance: 1.2,
+    ///     ..SiteCriteria::default()
+    /// };
+    /// let lattice = Lattice::from_basis_vectors(2, 1, [3.0, 0.0, 0.0], [0.0, 10.0, 0.0])?
+    ///     .with_geometry(geometry)
+    ///     .with_criteria(criteria);
+    /// let singlets = lattice.sites().filter(|s| s.kind == SiteKind::Singlet).collect::<Vec<_>>();
+    /// for singlet in &singlets {
+    ///     for other in singlets.iter().filter(|other| other.index != singlet.index) {
+    ///         assert!(!singlet.exclusions.contains(&other.index));
+    ///     }
+    /// }
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    pub min_crown_distance: f32,
+}
+
+impl SiteCriteria {
+    /// Accepts every group of points.
+    pub const ANY: Self = Self {
+        midpoint_distance: Window::ANY,
+        tripoint_distance: Window::ANY,
+        tripoint_angle: Window::ANY,
+        max_strain: f32::INFINITY,
+        min_site_distance: 0.0,
+        min_crown_distance: 0.0,
+    };
+
+    /// Checks the positions of the `points` on which a site of `kind` would be
+    /// placed, along `normal`. Only midpoints and tripoints are checked.
+    ///
+    /// # Errors
+    /// Returns the first rule the points break.
+    pub fn check(
+        &self,
+        kind: &SiteKind,
+        points: &[[f32; 3]],
+        normal: [f32; 3],
+    ) -> Result<(), Rejection> {
+        match (kind, points) {
+            (SiteKind::Midpoint, [one, two]) => {
+                check_distance(self.midpoint_distance, norm(sub(*two, *one)))
+            }
+            (SiteKind::Tripoint, &[one, two, three]) => {
+                let corners = [(one, two, three), (two, three, one), (three, one, two)];
+                for (corner, next, _) in corners {
+                    check_distance(self.tripoint_distance, norm(sub(next, corner)))?;
+                }
+                for (corner, next, previous) in corners {
+                    let angle = angle_between(sub(next, corner), sub(previous, corner));
+                    if !self.tripoint_angle.contains(angle) {
+                        return Err(Rejection::Angle(angle));
+                    }
+                }
+                let sides = corners.map(|(corner, next, _)| {
+                    let side = sub(next, corner);
+                    norm(sub(side, scale(normal, dot(side, normal))))
+                });
+                let mean = sides.iter().sum::<f32>() / 3.0;
+                let strain = sides
+                    .iter()
+                    .map(|side| (side - mean).abs() / mean)
+                    .fold(0.0, f32::max);
+                if strain > self.max_strain {
+                    return Err(Rejection::Strain(strain));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for SiteCriteria {
+    fn default() -> Self {
+        Self::ANY
+    }
+}
+
+fn check_distance(window: Window, distance: f32) -> Result<(), Rejection> {
+    if window.contains(distance) {
+        Ok(())
+    } else {
+        Err(Rejection::Distance(distance))
+    }
+}
+
+/// The angle between two vectors in degrees, or zero when either has no
+/// length.
+fn angle_between(one: [f32; 3], two: [f32; 3]) -> f32 {
+    match (normalize(one), normalize(two)) {
+        (Some(one), Some(two)) => dot(one, two).clamp(-1.0, 1.0).acos().to_degrees(),
+        _ => 0.0,
+    }
+}
+
+/// The rule a rejected group of points broke, with the offending value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rejection {
+    /// Two points are too close together or too far apart, in Å.
+    Distance(f32),
+    /// An angle of the triangle is too small or too large, in degrees.
+    Angle(f32),
+    /// The triangle is too distorted in the plane of the site.
+    Strain(f32),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Distance(distance) => write!(f, "points are {distance:.3} Å apart"),
+            Self::Angle(angle) => write!(f, "triangle has an angle of {angle:.1}°"),
+            Self::Strain(strain) => write!(f, "triangle has an in-plane strain of {strain:.3}"),
+        }
+    }
+}
+
+/// A group of attachment points on which no site was placed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedSite {
+    pub kind: SiteKind,
+    pub points: Vec<LatticeIndex>,
+    pub reason: Rejection,
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+/// Errors which can occur while creating or exporting a `Lattice`.
+#[derive(Debug)]
+pub enum LatticeError {
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// The file could not be parsed as json.
+    Json(json::Error),
+    /// A required key is missing from the ASE json file.
+    MissingKey(String),
+    /// An `__ndarray__` block contains values of the wrong type.
+    MalformedArray(String),
+    /// The `cell` property does not contain three vectors of three values.
+    CellShape(String),
+    /// Input points must be two or three dimentional.
+    PointDimension(usize),
+    /// No attachment sites were found in the input.
+    NoAttachmentSites,
+    /// The operation requires a `Lattice` created from an ASE json file.
+    NotFromFile,
+    /// A saved file is inconsistent or lacks the given property.
+    InvalidSave(String),
+    /// A saved file was written with an unsupported version of the save format.
+    UnsupportedVersion(usize),
+    /// A checkpoint was written by a different solve, which differs in the
+    /// given property.
+    CheckpointMismatch(String),
+    /// A `SiteGeometry` contains a value of the wrong type under the given key.
+    InvalidGeometry(String),
+}
+
+impl fmt::Display for LatticeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "file operation failed: {err}"),
+            Self::Json(err) => write!(f, "parsing json failed: {err}"),
+            Self::MissingKey(key) => write!(f, "ASE json is missing key '{key}'"),
+            Self::MalformedArray(key) => write!(f, "ASE json array '{key}' is malformed"),
+            Self::CellShape(cell) => write!(f, "json 'cell' property format is incorrect: {cell}"),
+            Self::PointDimension(dim) => write!(
+                f,
+                "input lattice layout is incorrect: points must be two or threedimentional, not {dim}"
+            ),
+            Self::NoAttachmentSites => write!(f, "no attachment sites found"),
+            Self::NotFromFile => write!(f, "lattice was not created from a file"),
+            Self::InvalidSave(what) => write!(f, "saved file is invalid: {what}"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "saved file has version {version}, only version {} is supported",
+                crate::save::SAVE_VERSION
+            ),
+            Self::CheckpointMismatch(what) => {
+                write!(f, "checkpoint belongs to a solve with different {what}")
+            }
+            Self::InvalidGeometry(key) => write!(f, "site geometry key '{key}' is invalid"),
+        }
+    }
+}
+
+impl std::error::Error for LatticeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LatticeError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<json::Error> for LatticeError {
+    fn from(err: json::Error) -> Self {
+        Self::Json(err)
+    }
+}
@@ -0,0 +1,154 @@
+//! The kinds of candidate sites, and the generators which create them.
+//!
+//! A `SiteGenerator` is offered every group of mutually neighbouring attachment
+//! points of the size it asks for, and decides whether a site is placed on
+//! them. The singlets, midpoints and tripoints are the built-in generators.
+use std::sync::Arc;
+
+use crate::{Lattice, LatticeIndex};
+
+/// The kind of a candidate site.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SiteKind {
+    /// A site connected to a single attachment point.
+    Singlet,
+    /// A site bridging two attachment points.
+    Midpoint,
+    /// A site bridging three attachment points.
+    Tripoint,
+    /// A site created by a custom `SiteGenerator`, identified by its name.
+    Custom(Arc<str>),
+}
+
+impl SiteKind {
+    /// The kinds of the built-in generators, in the order they are inserted.
+    pub const BUILT_IN: [Self; 3] = [Self::Tripoint, Self::Midpoint, Self::Singlet];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Singlet => "singlet",
+            Self::Midpoint => "midpoint",
+            Self::Tripoint => "tripoint",
+            Self::Custom(name) => name,
+        }
+    }
+
+    /// The kind with the given name. Unknown names are custom kinds.
+    pub fn from_name(name: &str) -> Self {
+        Self::BUILT_IN
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .unwrap_or_else(|| Self::Custom(name.into()))
+    }
+
+    /// The amount of attachment points connected by a site of a built-in kind.
+    pub const fn points(&self) -> Option<usize> {
+        match self {
+            Self::Singlet => Some(1),
+            Self::Midpoint => Some(2),
+            Self::Tripoint => Some(3),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+/// Creates the candidate sites of one `SiteKind`.
+///
+/// ```
+/// use crystacean_rs::{test_points::small_points, Lattice, LatticeIndex};
+/// use crystacean_rs::{SiteGenerator, SiteKind, SingletGenerator};
+///
+/// /// Singlets, but only on the points left of `x = 1.0`.
+/// struct LeftSinglets;
+///
+/// impl SiteGenerator for LeftSinglets {
+///     fn kind(&self) -> SiteKind {
+///         SiteKind::Custom("left".into())
+///     }
+///     fn points(&self) -> usize {
+///         1
+///     }
+///     fn place(&self, lattice: &Lattice, points: &[LatticeIndex]) -> Option<[f32; 3]> {
+///         let position = SingletGenerator.place(lattice, points)?;
+///         (position[0] < 1.0).then_some(position)
+///     }
+/// }
+///
+/// let lattice = Lattice::python_new(small_points(), 1.1, true)?
+///     .with_generators(vec![Box::new(LeftSinglets)]);
+/// assert!(lattice.sites().all(|site| site.position[0] < 1.0));
+/// assert_eq!(lattice.sites().count(), 1);
+/// # Ok::<(), crystacean_rs::LatticeError>(())
+/// ```
+pub trait SiteGenerator: Send + Sync {
+    /// The kind of the sites created by this generator.
+    fn kind(&self) -> SiteKind;
+
+    /// The amount of attachment points connected by a single site.
+    fn points(&self) -> usize;
+
+    /// The position of a site connecting `points`, or `None` when no site
+    /// should be placed. The points are all within the search distance of each
+    /// other, and at least one of them is not a ghost.
+    fn place(&self, lattice: &Lattice, points: &[LatticeIndex]) -> Option<[f32; 3]>;
+}
+
+/// Places a site on every attachment point.
+pub struct SingletGenerator;
+
+/// Places a site between every pair of neighbouring attachment points.
+pub struct MidpointGenerator;
+
+/// Places a site between every triangle of neighbouring attachment points.
+pub struct TripointGenerator;
+
+impl SiteGenerator for SingletGenerator {
+    fn kind(&self) -> SiteKind {
+        SiteKind::Singlet
+    }
+
+    fn points(&self) -> usize {
+        1
+    }
+
+    fn place(&self, lattice: &Lattice, points: &[LatticeIndex]) -> Option<[f32; 3]> {
+        lattice.place_site(&self.kind(), points)
+    }
+}
+
+impl SiteGenerator for MidpointGenerator {
+    fn kind(&self) -> SiteKind {
+        SiteKind::Midpoint
+    }
+
+    fn points(&self) -> usize {
+        2
+    }
+
+    fn place(&self, lattice: &Lattice, points: &[LatticeIndex]) -> Option<[f32; 3]> {
+        lattice.place_site(&self.kind(), points)
+    }
+}
+
+impl SiteGenerator for TripointGenerator {
+    fn kind(&self) -> SiteKind {
+        SiteKind::Tripoint
+    }
+
+    fn points(&self) -> usize {
+        3
+    }
+
+    fn place(&self, lattice: &Lattice, points: &[LatticeIndex]) -> Option<[f32; 3]> {
+        lattice.place_site(&self.kind(), points)
+    }
+}
+
+/// The built-in generators, in the order in which their sites are inserted.
+pub fn default_generators() -> Vec<Box<dyn SiteGenerator>> {
+    vec![
+        Box::new(TripointGenerator),
+        Box::new(MidpointGenerator),
+        Box::new(SingletGenerator),
+    ]
+}
@@ -0,0 +1,386 @@
+//! The chemistry profile which determines where new atoms are placed.
+//!
+//! A `SiteGeometry` can be created in code or loaded from a json file, so the
+//! geometry can be tuned without recompiling. An example file, which places
+//! singlets by bond length and adds a custom site kind:
+//! ```json
+//! {
+//!     "site_element": 14,
+//!     "crown_element": 1,
+//!     "bridge_element": 8,
+//!     "merge_distance": 1.1,
+//!     "singlet": { "bond_length": 1.7, "crown": [{ "bond_length": 1.49, "angle": 70.5 }] },
+//!     "midpoint": { "depth": 1.4 },
+//!     "tripoint": { "depth": 1.1, "crown": [{ "radius": 0.0, "depth": 1.7 }] },
+//!     "custom": { "fourfold": { "depth": 0.9, "crown": [] } }
+//! }
+//! ```
+//! Missing keys in the file fall back to `SiteGeometry::default()`, the
+//! silicon dioxide profile: singlets, midpoints and tripoints are placed at
+//! depths of 1.7, 1.4 and 1.1, with crowns of three, two and one arms given
+//! by `radius` and `depth`. `SiteGeometry::to_json` writes out the full
+//! profile. A crown with a single arm in a file is repeated to the amount of
+//! arms of the default crown of that site kind. The profiles of custom site
+//! kinds are stored by name under `custom`, and have no default.
+use json::{object, JsonValue};
+use std::{collections::BTreeMap, f32::consts::PI, fs::File, io::prelude::*};
+
+use crate::{vector::*, LatticeError, SiteKind, DOWN};
+
+/// How far a new atom is placed from the attachment points it connects to.
+/// The atom is placed from the centre of its attachment points, along the
+/// direction given by `local_normal`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// A fixed distance from the centre of the attachment points.
+    Depth(f32),
+    /// The distance between the new atom and each of its attachment points.
+    /// Falls back to the centre of the points when they are too far apart.
+    BondLength(f32),
+}
+
+/// An atom of the crown of a new atom, relative to the new atom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrownArm {
+    /// Distance from the crown atom to the axis through the new atom.
+    pub radius: f32,
+    /// Distance of the crown atom from the new atom along the axis.
+    pub depth: f32,
+}
+
+impl CrownArm {
+    pub const fn new(radius: f32, depth: f32) -> Self {
+        Self { radius, depth }
+    }
+
+    /// A crown arm with the given bond length, at `angle` radians from the
+    /// downward axis.
+    pub fn from_bond(bond_length: f32, angle: f32) -> Self {
+        Self {
+            radius: bond_length * angle.sin(),
+            depth: bond_length * angle.cos(),
+        }
+    }
+}
+
+/// The placement and crown of one kind of site.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteProfile {
+    pub placement: Placement,
+    /// The crown atoms, which are spread evenly around the new atom.
+    pub crown: Vec<CrownArm>,
+}
+
+/// The chemistry profile used to place new atoms and their crowns.
+///
+/// ```
+/// use crystacean_rs::{test_points::small_points, geometry::SiteGeometry, Lattice};
+///
+/// // Silicon atoms 1.65 Å from each oxygen, with tetrahedral hydrogen crowns.
+/// let geometry = SiteGeometry::tetrahedral(1.65, 1.48);
+/// let lattice = Lattice::python_new(small_points(), 1.1, true)?.with_geometry(geometry);
+/// let singlet = lattice.sites().last().unwrap();
+/// assert!((singlet.position[2] + 1.65).abs() < 1e-6);
+/// # Ok::<(), crystacean_rs::LatticeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteGeometry {
+    /// Atomic number of the new atoms.
+    pub site_element: i64,
+    /// Atomic number of the crown atoms.
+    pub crown_element: i64,
+    /// Atomic number of the atoms which replace the attachment markers, and
+    /// of the atom which replaces two overlapping crown atoms.
+    pub bridge_element: i64,
+    /// Crown atoms of different sites closer than this distance are merged
+    /// into a single bridge atom.
+    pub merge_distance: f32,
+    pub singlet: SiteProfile,
+    pub midpoint: SiteProfile,
+    pub tripoint: SiteProfile,
+    /// The profiles of custom site kinds, by name.
+    pub custom: BTreeMap<String, SiteProfile>,
+}
+
+impl Default for SiteGeometry {
+    /// Silicon dioxide, with the crown taken from a relaxed structure.
+    fn default() -> Self {
+        let triple_depth = 0.517599594;
+        let triple_radius = 1.3977189394504965;
+        let double_radius = 1.2091535111977374;
+        Self {
+            site_element: 14,
+            crown_element: 1,
+            bridge_element: 8,
+            merge_distance: 1.1,
+            singlet: SiteProfile {
+                placement: Placement::Depth(1.7),
+                crown: vec![CrownArm::new(triple_radius, triple_depth); 3],
+            },
+            midpoint: SiteProfile {
+                placement: Placement::Depth(1.4),
+                crown: vec![
+                    CrownArm::new(double_radius, 0.8443928979058786),
+                    CrownArm::new(double_radius, 0.9506360988307332),
+                ],
+            },
+            tripoint: SiteProfile {
+                placement: Placement::Depth(1.1),
+                crown: vec![CrownArm::new(0.0, 1.7)],
+            },
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+impl SiteGeometry {
+    /// A profile for tetrahedrally coordinated new atoms, placed at
+    /// `bond_length` from each attachment point, with crown atoms at
+    /// `crown_bond_length`.
+    pub fn tetrahedral(bond_length: f32, crown_bond_length: f32) -> Self {
+        // The angle between two bonds of a tetrahedron.
+        let bond_angle = (-1.0f32 / 3.0).acos();
+        // The crown bonds point away from the bonds to the attachment points.
+        let single = PI - bond_angle;
+        let double = bond_angle / 2.0;
+        Self {
+            singlet: SiteProfile {
+                placement: Placement::BondLength(bond_length),
+                crown: vec![CrownArm::from_bond(crown_bond_length, single); 3],
+            },
+            midpoint: SiteProfile {
+                placement: Placement::BondLength(bond_length),
+                crown: vec![CrownArm::from_bond(crown_bond_length, double); 2],
+            },
+            tripoint: SiteProfile {
+                placement: Placement::BondLength(bond_length),
+                crown: vec![CrownArm::from_bond(crown_bond_length, 0.0)],
+            },
+            ..Self::default()
+        }
+    }
+
+    /// The profile of the given kind, which is missing for custom kinds
+    /// without a profile.
+    pub fn profile(&self, kind: &SiteKind) -> Option<&SiteProfile> {
+        match kind {
+            SiteKind::Singlet => Some(&self.singlet),
+            SiteKind::Midpoint => Some(&self.midpoint),
+            SiteKind::Tripoint => Some(&self.tripoint),
+            SiteKind::Custom(name) => self.custom.get(&**name),
+        }
+    }
+
+    /// The position of a new atom of the given kind, connected to `points`,
+    /// placed along `normal` from the centre of the points. Returns `None` when
+    /// the kind has no profile.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn place(
+        &self,
+        kind: &SiteKind,
+        points: &[[f32; 3]],
+        normal: [f32; 3],
+    ) -> Option<[f32; 3]> {
+        let centre = scale(
+            points.iter().fold([0.0; 3], |acc, point| add(acc, *point)),
+            1.0 / points.len() as f32,
+        );
+
+        let depth = match self.profile(kind)?.placement {
+            Placement::Depth(depth) => depth,
+            Placement::BondLength(length) => {
+                let spread = points
+                    .iter()
+                    .map(|point| {
+                        let offset = sub(*point, centre);
+                        norm(sub(offset, scale(normal, dot(offset, normal))))
+                    })
+                    .fold(0.0, f32::max);
+                length.mul_add(length, -spread.powi(2)).max(0.0).sqrt()
+            }
+        };
+        Some(add(centre, scale(normal, depth)))
+    }
+
+    /// The crown atoms of a new atom of the given kind, relative to the new
+    /// atom. The crown points along `normal`, with the first arm in the
+    /// direction of `reference` projected onto the plane normal to `normal`.
+    /// Kinds without a profile have no crown.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn crown(&self, kind: &SiteKind, normal: [f32; 3], reference: [f32; 3]) -> Vec<[f32; 3]> {
+        let project = |vector: [f32; 3]| normalize(sub(vector, scale(normal, dot(vector, normal))));
+        let first = project(reference)
+            .or_else(|| project([1.0, 0.0, 0.0]))
+            .or_else(|| project([0.0, 1.0, 0.0]))
+            .unwrap_or([1.0, 0.0, 0.0]);
+        let second = cross(first, normal);
+
+        let Some(SiteProfile { crown, .. }) = self.profile(kind) else {
+            return vec![];
+        };
+        let step = 2.0 * PI / crown.len() as f32;
+        crown
+            .iter()
+            .enumerate()
+            .map(|(number, arm)| {
+                let angle = number as f32 * step;
+                add(
+                    add(
+                        scale(first, arm.radius * angle.cos()),
+                        scale(second, arm.radius * angle.sin()),
+                    ),
+                    scale(normal, arm.depth),
+                )
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let profile = |profile: &SiteProfile| {
+            let mut data = JsonValue::new_object();
+            match profile.placement {
+                Placement::Depth(depth) => data["depth"] = depth.into(),
+                Placement::BondLength(length) => data["bond_length"] = length.into(),
+            }
+            data["crown"] = profile
+                .crown
+                .iter()
+                .map(|arm| object! { radius: arm.radius, depth: arm.depth })
+                .collect::<Vec<_>>()
+                .into();
+            data
+        };
+        let mut custom = JsonValue::new_object();
+        for (name, custom_profile) in &self.custom {
+            custom[name.as_str()] = profile(custom_profile);
+        }
+        object! {
+            site_element: self.site_element,
+            crown_element: self.crown_element,
+            bridge_element: self.bridge_element,
+            merge_distance: self.merge_distance,
+            singlet: profile(&self.singlet),
+            midpoint: profile(&self.midpoint),
+            tripoint: profile(&self.tripoint),
+            custom: custom,
+        }
+    }
+
+    /// Read a `SiteGeometry` from json, see the module documentation for the
+    /// format.
+    ///
+    /// # Errors
+    /// Returns an error when a key contains a value of the wrong type.
+    pub fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        let invalid = |key: &str| LatticeError::InvalidGeometry(key.into());
+        let default = Self::default();
+        let element = |key: &str, default: i64| {
+            if data[key].is_null() {
+                Ok(default)
+            } else {
+                data[key].as_i64().ok_or_else(|| invalid(key))
+            }
+        };
+        let float = |data: &JsonValue, key: &str| {
+            data[key]
+                .as_f64()
+                .map(|value| value as f32)
+                .ok_or_else(|| invalid(key))
+        };
+        let profile = |data: &JsonValue,
+                       key: &str,
+                       default: Option<&SiteProfile>|
+         -> Result<SiteProfile, LatticeError> {
+            let placement = if data.has_key("depth") {
+                Placement::Depth(float(data, "depth")?)
+            } else if data.has_key("bond_length") {
+                Placement::BondLength(float(data, "bond_length")?)
+            } else {
+                default.ok_or_else(|| invalid(key))?.placement
+            };
+            let crown = if data["crown"].is_null() {
+                default
+                    .map(|default| default.crown.clone())
+                    .unwrap_or_default()
+            } else {
+                let arms = data["crown"]
+                    .members()
+                    .map(|arm| {
+                        if arm.has_key("angle") {
+                            Ok(CrownArm::from_bond(
+                                float(arm, "bond_length")?,
+                                float(arm, "angle")?.to_radians(),
+                            ))
+                        } else {
+                            Ok(CrownArm::new(float(arm, "radius")?, float(arm, "depth")?))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, LatticeError>>()?;
+                match (default, &arms[..]) {
+                    (Some(_), []) => return Err(invalid(key)),
+                    (Some(default), &[arm]) => vec![arm; default.crown.len()],
+                    _ => arms,
+                }
+            };
+            Ok(SiteProfile { placement, crown })
+        };
+
+        Ok(Self {
+            site_element: element("site_element", default.site_element)?,
+            crown_element: element("crown_element", default.crown_element)?,
+            bridge_element: element("bridge_element", default.bridge_element)?,
+            merge_distance: if data["merge_distance"].is_null() {
+                default.merge_distance
+            } else {
+                float(data, "merge_distance")?
+            },
+            singlet: profile(&data["singlet"], "singlet", Some(&default.singlet))?,
+            midpoint: profile(&data["midpoint"], "midpoint", Some(&default.midpoint))?,
+            tripoint: profile(&data["tripoint"], "tripoint", Some(&default.tripoint))?,
+            custom: data["custom"]
+                .entries()
+                .map(|(name, custom)| Ok((name.to_string(), profile(custom, name, None)?)))
+                .collect::<Result<_, LatticeError>>()?,
+        })
+    }
+
+    /// Load a `SiteGeometry` from a json file.
+    ///
+    /// # Errors
+    /// Returns an error when the file can not be read or parsed.
+    pub fn load(filename: &str) -> Result<Self, LatticeError> {
+        let mut buffer = String::new();
+        File::open(filename)?.read_to_string(&mut buffer)?;
+        Self::from_json(&json::parse(&buffer)?)
+    }
+}
+
+/// The direction in which a new atom is placed from the attachment `points`,
+/// given the bond direction of every point.
+///
+/// A single point uses its own bond. Two points use their mean bond, made
+/// perpendicular to the line between the points. Three points use the normal
+/// of their plane, on the side of their mean bond.
+///
+/// ```
+/// # use crystacean_rs::geometry::local_normal;
+/// let tilted = [0.6, 0.0, -0.8];
+/// let normal = local_normal(&[[0.0, 0.0, 0.0], [2.0, 0.0, 0.0]], &[tilted, tilted]);
+/// assert!(normal[0].abs() < 1e-6 && (normal[2] + 1.0).abs() < 1e-6);
+/// ```
+pub fn local_normal(points: &[[f32; 3]], bonds: &[[f32; 3]]) -> [f32; 3] {
+    let mean = normalize(bonds.iter().fold([0.0; 3], |acc, bond| add(acc, *bond))).unwrap_or(DOWN);
+    let fitted = match points {
+        [one, two] => normalize(sub(*two, *one))
+            .and_then(|line| normalize(sub(mean, scale(line, dot(mean, line))))),
+        [one, two, three] => normalize(cross(sub(*two, *one), sub(*three, *one))).map(|normal| {
+            if dot(normal, mean) < 0.0 {
+                scale(normal, -1.0)
+            } else {
+                normal
+            }
+        }),
+        _ => None,
+    };
+    fitted.unwrap_or(mean)
+}
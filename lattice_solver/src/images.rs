@@ -0,0 +1,151 @@
+//! Generation of the periodic images of the attachment points of a cell.
+use itertools::Itertools;
+use kiddo::{float::kdtree::KdTree, SquaredEuclidean};
+
+use crate::{
+    vector::{add, cross, dot, norm, sub},
+    BINSIZE,
+};
+
+/// The periodic directions of a cell, as given by the ASE `pbc` flags.
+pub struct PeriodicDirections {
+    vectors: Vec<[f32; 3]>,
+}
+
+impl PeriodicDirections {
+    /// Keeps the cell vectors which are periodic and have a non-zero length.
+    pub fn new(cell: &[[f32; 3]; 3], pbc: [bool; 3]) -> Self {
+        Self {
+            vectors: cell
+                .iter()
+                .zip(pbc)
+                .filter(|(_, periodic)| *periodic)
+                .map(|(vector, _)| *vector)
+                .filter(|vector| norm(*vector) > f32::EPSILON)
+                .collect(),
+        }
+    }
+
+    /// Distance between the two faces of the cell which are crossed by the
+    /// periodic direction `index`, measured within the periodic subspace.
+    fn width(&self, index: usize) -> f32 {
+        let vector = self.vectors[index];
+        let others = self
+            .vectors
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .map(|(_, v)| *v)
+            .collect_vec();
+        match others[..] {
+            [] => norm(vector),
+            [other] => norm(cross(vector, other)) / norm(other),
+            [one, two] => {
+                let normal = cross(one, two);
+                dot(vector, normal).abs() / norm(normal)
+            }
+            _ => unreachable!("a cell has three vectors"),
+        }
+    }
+
+    /// Every lattice translation, except zero, which can move a point of the
+    /// cell to within `radius` of another point of the cell.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn translations(&self, radius: f32) -> Vec<[f32; 3]> {
+        (0..self.vectors.len())
+            .map(|index| {
+                let reach = (radius / self.width(index)).ceil() as i32 + 1;
+                -reach..=reach
+            })
+            .multi_cartesian_product()
+            .filter(|steps| steps.iter().any(|&step| step != 0))
+            .map(|steps| {
+                let mut translation = [0.0; 3];
+                for (step, vector) in steps.into_iter().zip(&self.vectors) {
+                    for (t, v) in translation.iter_mut().zip(vector) {
+                        *t = (step as f32).mul_add(*v, *t);
+                    }
+                }
+                translation
+            })
+            .collect()
+    }
+
+    /// Returns, for every point, its periodic images which lie within `radius`
+    /// of any of the points.
+    pub fn images(&self, points: &[[f32; 3]], radius: f32) -> Vec<Vec<[f32; 3]>> {
+        let translations = self.translations(radius);
+        let kdtree: KdTree<_, u64, 3, BINSIZE, u32> = (&points.to_vec()).into();
+        let radius_sq = radius.powi(2);
+
+        points
+            .iter()
+            .map(|point| {
+                translations
+                    .iter()
+                    .map(|t| [point[0] + t[0], point[1] + t[1], point[2] + t[2]])
+                    .filter(|image| {
+                        kdtree.nearest_one::<SquaredEuclidean>(image).distance <= radius_sq
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Squared distance from the first point to its nearest neighbour, which
+    /// can be a periodic image. Returns `None` when there is no neighbour.
+    pub fn nearest_neighbour_sq(&self, points: &[[f32; 3]]) -> Option<f32> {
+        let first = points.first()?;
+        // A periodic image of the first point itself is never further away
+        // than the shortest periodic cell vector.
+        let radius = self
+            .vectors
+            .iter()
+            .map(|vector| norm(*vector))
+            .chain(
+                points
+                    .iter()
+                    .skip(1)
+                    .map(|p| norm([p[0] - first[0], p[1] - first[1], p[2] - first[2]])),
+            )
+            .min_by(f32::total_cmp)?;
+
+        let translations = self.translations(radius);
+        points
+            .iter()
+            .cartesian_product(std::iter::once([0.0; 3]).chain(translations))
+            .map(|(p, t)| [p[0] + t[0], p[1] + t[1], p[2] + t[2]])
+            .map(|image| {
+                let delta = [
+                    image[0] - first[0],
+                    image[1] - first[1],
+                    image[2] - first[2],
+                ];
+                dot(delta, delta)
+            })
+            .filter(|distance| *distance > f32::EPSILON)
+            .min_by(f32::total_cmp)
+    }
+
+    /// For every point of `from`, the shortest vector to a point of `to`, or
+    /// to one of its periodic images, when that point is within `radius`.
+    pub fn nearest_vectors(
+        &self,
+        from: &[[f32; 3]],
+        to: &[[f32; 3]],
+        radius: f32,
+    ) -> Vec<Option<[f32; 3]>> {
+        let translations = std::iter::once([0.0; 3])
+            .chain(self.translations(radius))
+            .collect_vec();
+        from.iter()
+            .map(|point| {
+                to.iter()
+                    .cartesian_product(&translations)
+                    .map(|(other, t)| sub(add(*other, *t), *point))
+                    .filter(|vector| norm(*vector) <= radius)
+                    .min_by(|one, two| norm(*one).total_cmp(&norm(*two)))
+            })
+            .collect()
+    }
+}
@@ -23,8 +23,34 @@ use std::{ffi::OsString, fs::File, iter::zip, sync::Arc};
 use kiddo::{float::kdtree::KdTree, SquaredEuclidean};
 use std::io::prelude::*;
 
+mod ase;
+
+mod attachment;
+pub use attachment::{AttachmentSpec, MarkerRegion};
+
+mod error;
+pub use error::LatticeError;
+
 mod points;
 use points::*;
+pub use points::{LatticeIndex, OxygenIndex};
+
+mod accessors;
+pub use accessors::{AttachmentPoint, CandidateSite};
+
+mod generators;
+use generators::default_generators;
+pub use generators::{
+    MidpointGenerator, SingletGenerator, SiteGenerator, SiteKind, TripointGenerator,
+};
+
+mod periodic;
+use periodic::{PeriodicCell, Periodicity};
+
+mod images;
+use images::PeriodicDirections;
+
+mod vector;
 
 mod bit_representation;
 pub use bit_representation::*;
@@ -35,6 +61,17 @@ use inserters::*;
 mod crown;
 use crown::*;
 
+mod save;
+pub use save::SAVE_VERSION;
+
+mod stable_hash;
+use stable_hash::StableHasher;
+
+pub mod basis_vectors;
+pub mod criteria;
+use criteria::{RejectedSite, Rejection, SiteCriteria};
+pub mod geometry;
+use geometry::SiteGeometry;
 pub mod close_vector_tree;
 // use close_vector_tree::*;
 
@@ -42,13 +79,33 @@ pub mod site_filter;
 pub mod test_points;
 
 const BINSIZE: usize = 129;
-const H_MARGIN: f32 = 1.1 * 1.1;
+/// Sites closer than this distance are at the same position. Sites of the same
+/// kind at the same position are periodic copies of each other, and are merged.
+const DUPLICATE_DISTANCE: f32 = 0.02;
+/// Markers further than this from every other atom keep the default bond
+/// direction.
+const BOND_SEARCH_RADIUS: f32 = 3.0;
+
+/// A point of the input lattice, together with the locations of its ghosts.
+pub type InputPoint = (Vec<f32>, Vec<Vec<f32>>);
 
 pub struct Lattice {
     points: Vec<Arc<LatticePoint>>,
     oxygens: Vec<Oxygen>,
     basis: Option<[[f32; 3]; 3]>,
+    /// Whether the lattice is periodic along each of the basis vectors.
+    pbc: [bool; 3],
+    periodicity: Periodicity,
     source_file: Option<JsonValue>,
+    attachment: AttachmentSpec,
+    geometry: SiteGeometry,
+    criteria: SiteCriteria,
+    /// The groups of points which did not satisfy `criteria`.
+    rejected: Vec<RejectedSite>,
+    /// The kinds of the candidate sites, in the order of their generators.
+    kinds: Vec<SiteKind>,
+    /// Squared distance within which points are considered neighbours.
+    node_search_distance: f32,
 }
 
 impl Lattice {
@@ -58,7 +115,15 @@ impl Lattice {
             points: vec![],
             oxygens: vec![],
             basis: None,
+            pbc: [true, true, false],
+            periodicity: Periodicity::default(),
             source_file: None,
+            attachment: AttachmentSpec::default(),
+            geometry: SiteGeometry::default(),
+            criteria: SiteCriteria::default(),
+            rejected: vec![],
+            kinds: SiteKind::BUILT_IN.to_vec(),
+            node_search_distance: 0.0,
         }
     }
 
@@ -67,28 +132,211 @@ impl Lattice {
         self.points.push(new_point);
     }
 
-    fn add_basis(&mut self, basis: [[f32; 3]; 3]) -> Result<(), ()> {
-        match self.basis {
-            None => {
-                self.basis = Some(basis);
-                Ok(())
+    /// The direction in which a new atom connected to `points` is placed,
+    /// fitted to the bonds of those points.
+    fn site_normal(&self, points: &[LatticeIndex]) -> [f32; 3] {
+        let (positions, bonds): (Vec<_>, Vec<_>) = points
+            .iter()
+            .map(|index| {
+                let point = &self.points[index.0];
+                ([point.x, point.y, point.z], point.bond)
+            })
+            .unzip();
+        geometry::local_normal(&positions, &bonds)
+    }
+
+    /// The position of a new atom of the given kind connected to `points`,
+    /// according to the `SiteGeometry` of the `Lattice`. Returns `None` when the
+    /// geometry has no profile for `kind`.
+    pub fn place_site(&self, kind: &SiteKind, points: &[LatticeIndex]) -> Option<[f32; 3]> {
+        let positions = points
+            .iter()
+            .map(|index| {
+                let point = &self.points[index.0];
+                [point.x, point.y, point.z]
+            })
+            .collect_vec();
+        self.geometry
+            .place(kind, &positions, self.site_normal(points))
+    }
+
+    /// Replace the `SiteGeometry` used to place new atoms and their crowns.
+    /// The positions of all candidate sites of which the kind has a profile are
+    /// recalculated and wrapped into the cell, and so are the exclusions which
+    /// depend on them.
+    ///
+    /// # Panics
+    /// Will panic when the connections of a point are poisoned.
+    pub fn set_geometry(&mut self, geometry: SiteGeometry) {
+        self.geometry = geometry;
+        for number in 0..self.oxygens.len() {
+            let sitetype = &self.oxygens[number].sitetype;
+            if let Some(position) = self.place_site(&sitetype.kind, sitetype.as_slice()) {
+                let [x, y, z] = self.periodicity.wrap(position);
+                let oxygen = &mut self.oxygens[number];
+                oxygen.x = x;
+                oxygen.y = y;
+                oxygen.z = z;
             }
-            Some(_) => Err(()),
         }
+        self.generate_exclusions();
     }
 
-    fn generate_exclusions(&mut self) {
-        let oxygen_amount = self.oxygens.len();
-        let mut distances_matrix = vec![];
+    /// Builder version of `Lattice::set_geometry`.
+    #[must_use]
+    pub fn with_geometry(mut self, geometry: SiteGeometry) -> Self {
+        self.set_geometry(geometry);
+        self
+    }
 
-        for number in 0..oxygen_amount {
-            let mut distances_row = vec![];
-            for other in 0..oxygen_amount {
-                distances_row.push(self.distance_between(OxygenIndex(number), OxygenIndex(other)));
+    /// Checks a group of points on which a site of `kind` would be placed
+    /// against the `SiteCriteria` of the `Lattice`.
+    fn check_site(&self, kind: &SiteKind, points: &[LatticeIndex]) -> Result<(), Rejection> {
+        let positions = points
+            .iter()
+            .map(|index| {
+                let point = &self.points[index.0];
+                [point.x, point.y, point.z]
+            })
+            .collect_vec();
+        self.criteria
+            .check(kind, &positions, self.site_normal(points))
+    }
+
+    /// Replace the `SiteCriteria` which midpoints and tripoints have to satisfy.
+    /// Candidate sites which do not satisfy `criteria` are removed and added to
+    /// `Lattice::rejected_sites`, and the exclusions between the remaining
+    /// sites are regenerated. Sites removed by earlier criteria are only
+    /// restored by `Lattice::set_generators`.
+    ///
+    /// # Panics
+    /// Will panic when the connections of a point are poisoned.
+    pub fn set_criteria(&mut self, criteria: SiteCriteria) {
+        self.criteria = criteria;
+        for oxygen in std::mem::take(&mut self.oxygens) {
+            let sitetype = &oxygen.sitetype;
+            match self.check_site(&sitetype.kind, sitetype.as_slice()) {
+                Ok(()) => self.oxygens.push(oxygen),
+                Err(reason) => self.rejected.push(RejectedSite {
+                    kind: oxygen.sitetype.kind,
+                    points: oxygen.sitetype.points,
+                    reason,
+                }),
             }
-            distances_matrix.push(distances_row);
+        }
+        self.generate_exclusions();
+    }
+
+    /// Builder version of `Lattice::set_criteria`.
+    #[must_use]
+    pub fn with_criteria(mut self, criteria: SiteCriteria) -> Self {
+        self.set_criteria(criteria);
+        self
+    }
+
+    /// The groups of points on which no site was placed, because they did not
+    /// satisfy the `SiteCriteria` of the `Lattice`.
+    pub fn rejected_sites(&self) -> &[RejectedSite] {
+        &self.rejected
+    }
+
+    /// Replace the candidate sites by the sites of `generators`, which are
+    /// inserted in the given order. Groups of points which do not satisfy the
+    /// `SiteCriteria` of the `Lattice` are skipped.
+    ///
+    /// Sites built from ghosts can be periodic images of each other. Every
+    /// site is wrapped into the cell, and only the first of its images is
+    /// kept.
+    ///
+    /// # Panics
+    /// Will panic when the connections of a point are poisoned.
+    pub fn set_generators(&mut self, generators: &[Box<dyn SiteGenerator>]) {
+        self.oxygens.clear();
+        self.rejected.clear();
+        self.kinds = generators.iter().map(|g| g.kind()).unique().collect();
+
+        let positions = self.points.iter().map(|p| [p.x, p.y, p.z]).collect_vec();
+        let kdtree: KdTree<_, u64, 3, BINSIZE, u32> = KdTree::from(&positions);
+        for generator in generators {
+            insert_sites(self, generator.as_ref(), &kdtree, self.node_search_distance);
+        }
+        self.merge_periodic_copies();
+        self.generate_exclusions();
+    }
+
+    /// Removes the candidate sites which are a periodic copy of an earlier
+    /// site of the same kind.
+    fn merge_periodic_copies(&mut self) {
+        let positions = self.oxygens.iter().map(Oxygen::position).collect_vec();
+        let neighbours = self.periodicity.neighbours(&positions, DUPLICATE_DISTANCE);
+        let mut copies = FixedBitSet::with_capacity(self.oxygens.len());
+        for (number, close) in neighbours.iter().enumerate() {
+            if copies[number] {
+                continue;
+            }
+            for &other in close.iter().filter(|&&other| other > number) {
+                if self.oxygens[other].sitetype.kind == self.oxygens[number].sitetype.kind {
+                    copies.insert(other);
+                }
+            }
+        }
+        self.oxygens = std::mem::take(&mut self.oxygens)
+            .into_iter()
+            .enumerate()
+            .filter(|(number, _)| !copies[*number])
+            .map(|(_, oxygen)| oxygen)
+            .collect();
+    }
+
+    /// Builder version of `Lattice::set_generators`.
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn with_generators(mut self, generators: Vec<Box<dyn SiteGenerator>>) -> Self {
+        self.set_generators(&generators);
+        self
+    }
+
+    /// Connects the sites to their points, and regenerates the exclusions of
+    /// all sites.
+    fn generate_exclusions(&mut self) {
+        for point in &self.points {
+            point.connected_to.write().unwrap().clear();
+        }
+        for oxygen in &mut self.oxygens {
+            oxygen.exclusions.clear();
         }
 
+        // The crown of every site, for the steric exclusions.
+        let crowns = if self.criteria.min_crown_distance > 0.0 {
+            self.oxygens
+                .iter()
+                .map(|oxygen| self.crown_positions(oxygen))
+                .collect_vec()
+        } else {
+            vec![]
+        };
+        let min_site_distance = self.criteria.min_site_distance.max(DUPLICATE_DISTANCE);
+        let min_crown_distance = self.criteria.min_crown_distance;
+        let merge_distance = self.geometry.merge_distance;
+
+        // Only sites within this distance of each other can be close enough to
+        // exclude each other.
+        let positions = self.oxygens.iter().map(Oxygen::position).collect_vec();
+        let crown_reach = zip(&crowns, &positions)
+            .flat_map(|(crown, position)| {
+                crown
+                    .iter()
+                    .map(|atom| vector::norm(vector::sub(*atom, *position)))
+            })
+            .fold(0.0, f32::max);
+        let search_radius = if min_crown_distance > 0.0 {
+            min_site_distance.max(2.0f32.mul_add(crown_reach, min_crown_distance))
+        } else {
+            min_site_distance
+        };
+        let neighbours = self.periodicity.neighbours(&positions, search_radius);
+        let periodicity = &self.periodicity;
+
         for (number, oxygen) in self.oxygens.iter().enumerate() {
             for index in oxygen.sitetype.iter() {
                 let mut connections = self.points[index.0].get_connections().write().unwrap();
@@ -105,10 +353,30 @@ impl Lattice {
                 }
             }
             // points which are to close to one another should exclude eachother
-            for other in 0..oxygen_amount {
-                if distances_matrix[number][other] < 0.02 && number != other {
-                    oxygen.exclusions.push(OxygenIndex(other));
+            for &other in &neighbours[number] {
+                let distance = periodicity.distance(&positions[number], &positions[other]);
+                // The new atom and the crown atoms of a site, where crown atoms
+                // are marked `true`.
+                let atoms = |site: usize| {
+                    crowns[site]
+                        .iter()
+                        .map(|atom| (atom, true))
+                        .chain([(&positions[site], false)])
+                };
+                // Crown atoms within the merge distance of each other are
+                // merged into a bridge on export, so they do not collide.
+                let crowded = || {
+                    atoms(number).cartesian_product(atoms(other)).any(
+                        |((one, one_is_crown), (two, two_is_crown))| {
+                            let distance = periodicity.distance(one, two);
+                            distance < min_crown_distance
+                                && !(one_is_crown && two_is_crown && distance < merge_distance)
+                        },
+                    )
                 };
+                if distance < min_site_distance || (min_crown_distance > 0.0 && crowded()) {
+                    oxygen.exclusions.push(OxygenIndex(other));
+                }
             }
             oxygen.exclusions.dedup();
         }
@@ -118,11 +386,12 @@ impl Lattice {
     fn generate_intermediary(&self, options: BitArraySettings) -> BitArrayRepresentation {
         let filled_sites = FixedBitSet::with_capacity(self.oxygens.len());
         let mut exclusion_matrix: Vec<FixedBitSet> = vec![];
-        let mut distances_matrix = vec![];
 
-        let mut tripoint_mask = FixedBitSet::with_capacity(self.oxygens.len());
-        let mut midpoint_mask = FixedBitSet::with_capacity(self.oxygens.len());
-        let mut singlet_mask = FixedBitSet::with_capacity(self.oxygens.len());
+        let mut kind_masks = self
+            .kinds
+            .iter()
+            .map(|kind| (kind.clone(), FixedBitSet::with_capacity(self.oxygens.len())))
+            .collect_vec();
 
         for (number, oxygen) in self.oxygens.iter().enumerate() {
             let mut exclusions = FixedBitSet::with_capacity(self.oxygens.len());
@@ -130,34 +399,26 @@ impl Lattice {
                 exclusions.set(exclusion.0, true);
             }
 
-            let mut distances_row = vec![];
-            for other in 0..self.oxygens.len() {
-                distances_row.push(if other > number {
-                    self.distance_between(OxygenIndex(number), OxygenIndex(other))
-                } else {
-                    0.0
-                });
+            if let Some((_, mask)) = kind_masks
+                .iter_mut()
+                .find(|(kind, _)| *kind == oxygen.sitetype.kind)
+            {
+                mask.set(number, true);
             }
-            distances_matrix.push(distances_row);
-
-            match &oxygen.sitetype {
-                SiteType::Tripoint(_) => tripoint_mask.set(number, true),
-                SiteType::Midpoint(_) => midpoint_mask.set(number, true),
-                SiteType::Singlet(_) => singlet_mask.set(number, true),
-            };
             exclusion_matrix.push(exclusions);
         }
 
-        BitArrayRepresentation {
+        BitArrayRepresentation::from_parts(
             filled_sites,
             exclusion_matrix,
-            distances_matrix,
-            tripoint_mask,
-            midpoint_mask,
-            singlet_mask,
-            filter: None,
+            SiteDistances::from_positions(
+                self.oxygens.iter().map(Oxygen::position).collect(),
+                self.periodicity.clone(),
+            ),
+            kind_masks,
+            None,
             options,
-        }
+        )
     }
 
     pub fn find_max(&self) -> (f32, f32) {
@@ -175,84 +436,99 @@ impl Lattice {
         (max_x, max_y)
     }
 
-    pub fn distance_between(&self, index_one: OxygenIndex, index_two: OxygenIndex) -> f32 {
-        let (max_x, max_y) = self.find_max();
-        let one = &self.oxygens[index_one.0];
-        let two = &self.oxygens[index_two.0];
-
-        let delta_x = {
-            let dx = (one.x - two.x).abs();
-            if dx > max_x / 2.0 {
-                dx - max_x
-            } else {
-                dx
-            }
+    /// The periodicity used for distances between points. Lattices without a
+    /// (non-degenerate) basis fall back to the rectangular box of `find_max`.
+    /// Only the first two basis vectors are considered periodic.
+    ///
+    /// Stored in `self.periodicity`, which has to be updated whenever the
+    /// points, basis or periodic directions change.
+    fn find_periodicity(&self) -> Periodicity {
+        let rectangular = || {
+            let (max_x, max_y) = self.find_max();
+            Periodicity::Rectangular(max_x, max_y)
         };
-        let delta_y = {
-            let dy = (one.y - two.y).abs();
-            if dy > max_y / 2.0 {
-                dy - max_y
-            } else {
-                dy
+        match (self.basis, self.pbc) {
+            (None, _) => rectangular(),
+            (Some(basis), [true, true, _]) => {
+                PeriodicCell::new(&basis).map_or_else(rectangular, Periodicity::Cell)
             }
-        };
+            (Some([a, _, _]), [true, false, _]) => Periodicity::Line(a),
+            (Some([_, b, _]), [false, true, _]) => Periodicity::Line(b),
+            (Some(_), [false, false, _]) => Periodicity::Open,
+        }
+    }
 
-        #[allow(clippy::suboptimal_flops)]
-        (delta_x.powi(2) + delta_y.powi(2) + (one.z - two.z).powi(2)).sqrt()
+    /// Minimum-image distance between two oxygens.
+    pub fn distance_between(&self, index_one: OxygenIndex, index_two: OxygenIndex) -> f32 {
+        self.periodicity.distance(
+            &self.oxygens[index_one.0].position(),
+            &self.oxygens[index_two.0].position(),
+        )
     }
 
-    #[allow(clippy::suboptimal_flops)]
+    /// Squared distance between two points, taking the periodic images of the
+    /// cell into account.
+    ///
+    /// ```
+    /// # use crystacean_rs::Lattice;
+    /// // Hexagonal cell with a = [3.076, -5.327, 0] and b = [3.076, 5.327, 0]
+    /// let lattice = Lattice::from_dft_json("../test_lattices/T04.json".into(), 1.1, true)?;
+    /// // The points are 0.251 Å apart through the image at a + b
+    /// let distance = lattice.distance_sq(&[0.1, 0.0, 15.0], &[6.0, 0.0, 15.0]);
+    /// assert!((distance - 0.251f32.powi(2)).abs() < 1e-3);
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
     pub fn distance_sq(&self, one: &[f32; 3], t: &[f32; 3]) -> f32 {
-        let null_vec = [0.0, 0.0, 0.0];
-        let x_vec = self.basis.unwrap()[0];
-        let y_vec = self.basis.unwrap()[1];
-        let vec_xy = [
-            x_vec[0] + y_vec[0],
-            x_vec[1] + y_vec[1],
-            x_vec[2] + y_vec[2],
-        ];
-        let basis = [null_vec, x_vec, y_vec, vec_xy];
-
-        let distance = |(o, t): ([f32; 3], [f32; 3])| {
-            (o[0] - t[0]).powi(2) + (o[1] - t[1]).powi(2) + (o[2] - t[2]).powi(2)
-        };
-
-        let one_vecs = std::iter::once(one)
-            .cycle()
-            .zip(basis)
-            .map(|(o, v)| [o[0] - v[0], o[1] - v[1], o[2] - v[2]]);
-
-        let two_vecs = std::iter::once(t)
-            .cycle()
-            .zip(basis)
-            .map(|(t, v)| [t[0] - v[0], t[1] - v[1], t[2] - v[2]]);
-
-        one_vecs
-            .cartesian_product(two_vecs)
-            .map(distance)
-            .min_by(|x, y| x.partial_cmp(y).unwrap())
-            .expect("No distances found.")
+        self.periodicity.distance_sq(one, t)
     }
 
     /// `distance_margin` should be 1.1 for 2D, 1.4 for 3D
-    #[must_use]
+    ///
+    /// # Errors
+    /// Returns an error when `input_lattice` is empty or its points are not two
+    /// or three dimentional.
     pub fn python_new(
-        input_lattice: Vec<(Vec<f32>, Vec<Vec<f32>>)>,
+        input_lattice: Vec<InputPoint>,
         distance_margin: f32,
         autodetect_margin: bool,
-    ) -> Self {
+    ) -> Result<Self, LatticeError> {
+        Self::build(
+            input_lattice,
+            None,
+            distance_margin,
+            autodetect_margin,
+            None,
+            [true, true, false],
+        )
+    }
+
+    /// Shared constructor behind `python_new` and the file based constructors.
+    ///
+    /// `bonds` holds the bond direction of every input point, and defaults to
+    /// straight down.
+    fn build(
+        input_lattice: Vec<InputPoint>,
+        bonds: Option<Vec<[f32; 3]>>,
+        distance_margin: f32,
+        autodetect_margin: bool,
+        basis: Option<[[f32; 3]; 3]>,
+        pbc: [bool; 3],
+    ) -> Result<Self, LatticeError> {
         // Convert 2D structures to 3D
-        let lattice_3d = turn_2d_3d(input_lattice);
+        let lattice_3d = turn_2d_3d(input_lattice)?;
+        let bonds = bonds.unwrap_or_else(|| vec![DOWN; lattice_3d.len()]);
 
         // Create the silicon lattice
-        let mut out_lattice = create_silicon_lattice(lattice_3d);
+        let mut out_lattice = create_silicon_lattice(lattice_3d, &bonds);
+        out_lattice.basis = basis;
+        out_lattice.pbc = pbc;
+        out_lattice.periodicity = out_lattice.find_periodicity();
 
         let first_point_location = {
             let first_point = &out_lattice.points[0];
             [first_point.x, first_point.y, first_point.z]
         };
 
-        // Fill in the oxygens
         let silicon_iterator = out_lattice
             .points
             .iter()
@@ -267,177 +543,215 @@ impl Lattice {
             distance_margin.powi(2)
         };
 
-        insert_tripoints(&mut out_lattice, &kdtree, node_search_distance);
-        insert_midpoints(&mut out_lattice, &kdtree, node_search_distance);
-        insert_singles(&mut out_lattice);
+        // Fill in the oxygens
+        out_lattice.node_search_distance = node_search_distance;
+        out_lattice.set_generators(&default_generators());
+        Ok(out_lattice)
+    }
 
-        out_lattice.generate_exclusions();
-        out_lattice
+    /// Create the `Lattice` of a flat supercell of `size_x` by `size_y` unit cells,
+    /// spanned by the basis vectors `a` and `b`. The cell of the supercell is
+    /// stored as the basis of the `Lattice`.
+    ///
+    /// Use `basis_vectors::SIC_A` and `basis_vectors::SIC_B` for a silicon carbide substrate.
+    ///
+    /// ```
+    /// # use crystacean_rs::{basis_vectors::{SIC_A, SIC_B}, Lattice};
+    /// let lattice = Lattice::from_basis_vectors(2, 1, SIC_A, SIC_B)?;
+    /// // 8 points, with 7 ghosts on the top and right boundaries
+    /// assert_eq!(lattice.points_to_plot().0.len(), 15);
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when either size is zero.
+    pub fn from_basis_vectors(
+        size_x: usize,
+        size_y: usize,
+        a: [f32; 3],
+        b: [f32; 3],
+    ) -> Result<Self, LatticeError> {
+        Self::build(
+            basis_vectors::lattice_from_basis_vectors(size_x, size_y, a, b),
+            None,
+            1.1,
+            true,
+            Some(basis_vectors::supercell(size_x, size_y, a, b)),
+            [true, true, false],
+        )
     }
 
     fn add_source_file(&mut self, source_file: JsonValue) {
         self.source_file = Some(source_file);
     }
 
-    /// Create a `Lattice` from an ASE json file.
+    /// Create a `Lattice` from an ASE json file, using the hydrogen atoms below
+    /// `z = 20.0` as attachment sites.
     ///
     /// Usefull for creating secondary layers on top of structures processed with CP2K.
     ///
-    /// # Panics
-    /// Will panic when supplied with missing or invalid file.
-    #[must_use]
-    pub fn from_dft_json(filename: String, distance_margin: f32, autodetect_margin: bool) -> Self {
+    /// # Errors
+    /// Returns an error when the file is missing, can not be parsed, lacks the
+    /// required ASE properties or contains no attachment sites.
+    ///
+    /// ```
+    /// # use crystacean_rs::{Lattice, LatticeError};
+    /// let missing = Lattice::from_dft_json("missing.json".into(), 1.1, true);
+    /// assert!(matches!(missing, Err(LatticeError::Io(_))));
+    /// ```
+    pub fn from_dft_json(
+        filename: String,
+        distance_margin: f32,
+        autodetect_margin: bool,
+    ) -> Result<Self, LatticeError> {
+        Self::from_dft_json_with(
+            filename,
+            distance_margin,
+            autodetect_margin,
+            AttachmentSpec::default(),
+        )
+    }
+
+    /// Create a `Lattice` from an ASE json file, using the atoms selected by
+    /// `attachment` as attachment sites. The same atoms are replaced when
+    /// exporting with `export_as_ase_json`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{AttachmentSpec, Lattice, MarkerRegion};
+    /// let spec = AttachmentSpec::default().region(MarkerRegion::Bottom(2.0));
+    /// let lattice = Lattice::from_dft_json_with("../test_lattices/T04.json".into(), 1.1, true, spec)?;
+    /// let default = Lattice::from_dft_json("../test_lattices/T04.json".into(), 1.1, true)?;
+    /// assert_eq!(lattice.points_to_plot(), default.points_to_plot());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when the file is missing, can not be parsed, lacks the
+    /// required ASE properties or no atoms match `attachment`.
+    pub fn from_dft_json_with(
+        filename: String,
+        distance_margin: f32,
+        autodetect_margin: bool,
+        attachment: AttachmentSpec,
+    ) -> Result<Self, LatticeError> {
         let mut buffer = String::new();
-        let mut file = File::open(filename).expect("Opening file failed.");
-        file.read_to_string(&mut buffer)
-            .expect("Reading file failed.");
-        let parsed = json::parse(&buffer).expect("Parsing file failed.");
-
-        let last_id = &parsed["ids"][parsed["ids"].len() - 1].to_string();
-
-        // let hydrogen_amount = &parsed[last_id]["numbers"]["__ndarray__"][2]
-        //     .members()
-        //     .filter_map(|n| n.as_usize())
-        //     .filter(|n| n == &1)
-        //     .count();
-
-        let numbers = &parsed[last_id]["positions"]["__ndarray__"][2];
-        let atoms = numbers
-            .members()
-            .map(|j| j.as_f32().unwrap())
-            .collect_vec()
-            .chunks_exact(3)
-            .map(<[f32]>::to_vec)
-            .zip(
-                parsed[last_id]["numbers"]["__ndarray__"][2]
-                    .members()
-                    .map(json::JsonValue::as_i32),
-            )
-            .collect_vec();
+        let mut file = File::open(filename)?;
+        file.read_to_string(&mut buffer)?;
+        let parsed = json::parse(&buffer)?;
+
+        let structure = ase::last_structure(&parsed)?;
+        let positions = ase::positions(structure)?;
+        let markers = attachment.select_in(structure)?;
+        let hydrogenated_ends = markers.iter().map(|&index| positions[index]).collect_vec();
+        if hydrogenated_ends.is_empty() {
+            return Err(LatticeError::NoAttachmentSites);
+        }
 
-        let hydrogenated_ends = atoms
+        let cell = ase::cell(structure)?;
+        let pbc = ase::pbc(structure)?;
+        let periodic = PeriodicDirections::new(&cell, pbc);
+
+        // Every marker is bonded to its nearest atom which is not a marker.
+        let substrate = positions
             .iter()
-            .filter(|(_, i)| i == &Some(1))
-            .map(|(v, _)| v)
-            .filter(|v| v[2] < 20.0)
+            .enumerate()
+            .filter(|(index, _)| !markers.contains(index))
+            .map(|(_, position)| *position)
+            .collect_vec();
+        let bonds = periodic
+            .nearest_vectors(&hydrogenated_ends, &substrate, BOND_SEARCH_RADIUS)
+            .into_iter()
+            .map(|to_substrate| {
+                to_substrate
+                    .and_then(|vector| vector::normalize(vector::scale(vector, -1.0)))
+                    .unwrap_or(DOWN)
+            })
             .collect_vec();
 
-        let cell = &parsed[last_id]["cell"]["array"]["__ndarray__"][2];
-        let (x_vec, y_vec, z_vec) = cell
-            .members()
-            .map(|v| v.as_f32().unwrap())
-            .tuples::<(_, _, _)>()
-            .collect_tuple()
-            .unwrap_or_else(|| panic!("Json 'cell' property format is incorrect: {cell:?}"));
-
-        let input_lattice = {
-            let mut input_lattice = vec![];
-            for end in hydrogenated_ends {
-                let mut new_point = (end.clone(), vec![]);
-                new_point
-                    .1
-                    .push(vec![end[0] + x_vec.0, end[1] + x_vec.1, end[2] + x_vec.2]);
-                new_point
-                    .1
-                    .push(vec![end[0] + y_vec.0, end[1] + y_vec.1, end[2] + y_vec.2]);
-                new_point.1.push(vec![
-                    end[0] + x_vec.0 + y_vec.0,
-                    end[1] + x_vec.1 + y_vec.1,
-                    end[2] + x_vec.2 + y_vec.2,
-                ]);
-                new_point.1.push(vec![
-                    end[0] + x_vec.0 - y_vec.0,
-                    end[1] + x_vec.1 - y_vec.1,
-                    end[2] + x_vec.2 - y_vec.2,
-                ]);
-                new_point.1.push(vec![
-                    end[0] - x_vec.0 + y_vec.0,
-                    end[1] - x_vec.1 + y_vec.1,
-                    end[2] - x_vec.2 + y_vec.2,
-                ]);
-                input_lattice.push(new_point);
-            }
-            input_lattice
+        // The images are generated before the kd-tree exists, so the margin is
+        // detected from the nearest (possibly periodic) neighbour instead.
+        let node_search_distance = match periodic.nearest_neighbour_sq(&hydrogenated_ends) {
+            Some(nearest) if autodetect_margin => nearest * distance_margin,
+            _ => distance_margin.powi(2),
         };
+        let search_radius = node_search_distance.sqrt();
 
-        let mut lattice = Self::python_new(input_lattice, distance_margin, autodetect_margin);
-        lattice
-            .add_basis([x_vec.into(), y_vec.into(), z_vec.into()])
-            .unwrap();
+        let input_lattice = zip(
+            &hydrogenated_ends,
+            periodic.images(&hydrogenated_ends, search_radius),
+        )
+        .map(|(end, images)| (end.to_vec(), images.iter().map(|i| i.to_vec()).collect()))
+        .collect_vec();
+
+        let mut lattice = Self::build(
+            input_lattice,
+            Some(bonds),
+            search_radius,
+            false,
+            Some(cell),
+            pbc,
+        )?;
         lattice.add_source_file(parsed.clone());
+        lattice.attachment = attachment;
 
-        lattice
+        Ok(lattice)
     }
 
     /// Output the `Lattice` for diagnostic purpouses.
     ///
-    /// # Panics
-    /// Can panic when `Lattice` was not created from file or file writing fails.
-    pub fn diagnostic_ase(&self) {
-        let parsed = self.source_file.as_ref().unwrap();
+    /// # Errors
+    /// Returns an error when `Lattice` was not created from file or file writing fails.
+    pub fn diagnostic_ase(&self) -> Result<(), LatticeError> {
+        let parsed = self.source_file.as_ref().ok_or(LatticeError::NotFromFile)?;
         let oxygens = &self.oxygens;
-        let last_id = &parsed["ids"][parsed["ids"].len() - 1].to_string();
+        let structure = ase::last_structure(parsed)?;
 
-        let mut new_numbers = parsed[last_id]["numbers"].clone();
-        let mut new_positions = parsed[last_id]["positions"].clone();
+        let mut new_numbers = structure["numbers"].clone();
+        let mut new_positions = structure["positions"].clone();
 
         println!("Added {} oxygens.", oxygens.len());
 
         for oxygen in oxygens {
-            new_numbers["__ndarray__"][2]
-                .push(8)
-                .expect("new_numbers[\"__ndarray__\"][2].push(8)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.x)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.x)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.y)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.y)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.z)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.z)");
+            new_numbers["__ndarray__"][2].push(8)?;
+            new_positions["__ndarray__"][2].push(oxygen.x)?;
+            new_positions["__ndarray__"][2].push(oxygen.y)?;
+            new_positions["__ndarray__"][2].push(oxygen.z)?;
         }
         new_numbers["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
         new_positions["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
 
         let mut export_data = json::JsonValue::new_object();
         export_data["1"] = object! {
-            cell: parsed[last_id]["cell"].clone(),
-            ctime: parsed[last_id]["ctime"].clone(),
-            mtime: parsed[last_id]["mtime"].clone(),
+            cell: structure["cell"].clone(),
+            ctime: structure["ctime"].clone(),
+            mtime: structure["mtime"].clone(),
             numbers: new_numbers,
-            pbc: parsed[last_id]["pbc"].clone(),
+            pbc: structure["pbc"].clone(),
             positions: new_positions,
             unique_id: "Not unique",
-            user: parsed[last_id]["user"].clone(),
+            user: structure["user"].clone(),
         };
 
-        let mut file = File::create("output.json").unwrap();
-        file.write_all(export_data.pretty(4).as_bytes()).unwrap();
+        let mut file = File::create("output.json")?;
+        file.write_all(export_data.pretty(4).as_bytes())?;
+        Ok(())
     }
 
     /// Helper method for the `no_rings` methods.
-    fn no_rings_detector(&self) -> (Vec<[f32; 3]>, Vec<usize>) {
-        let parsed = self.source_file.as_ref().unwrap();
-
-        let last_id = &parsed["ids"][parsed["ids"].len() - 1].to_string();
-
-        let numbers = &parsed[last_id]["positions"]["__ndarray__"][2];
-        let elements = &parsed[last_id]["numbers"]["__ndarray__"][2];
-        let atoms = numbers
-            .members()
-            .map(|j| j.as_f32().unwrap())
-            .collect_vec()
-            .chunks_exact(3)
-            .map(<[f32]>::to_vec)
-            .collect_vec();
+    fn no_rings_detector(&self) -> Result<(Vec<[f32; 3]>, Vec<usize>), LatticeError> {
+        let parsed = self.source_file.as_ref().ok_or(LatticeError::NotFromFile)?;
+        let structure = ase::last_structure(parsed)?;
+
+        let atoms = ase::positions(structure)?;
+        let elements = ase::numbers(structure)?;
+        if atoms.len() != elements.len() {
+            return Err(LatticeError::MalformedArray("numbers".into()));
+        }
 
-        let top_silicon_locations = atoms
-            .iter()
-            .zip_eq(elements.members())
-            .sorted_by(|&a, &b| a.0[2].total_cmp(&b.0[2]))
-            .filter(|(s, e)| e.as_i64().unwrap() == 14 && (s[2] < 10.0))
-            .map(|(v, _)| [v[0], v[1], v[2]])
+        let top_silicon_locations = zip(atoms, elements)
+            .sorted_by(|a, b| a.0[2].total_cmp(&b.0[2]))
+            .filter(|(s, e)| *e == 14 && (s[2] < 10.0))
+            .map(|(v, _)| v)
             .collect_vec();
 
         let points_vector = self.points.iter().map(|p| [p.x, p.y, p.z]).collect_vec();
@@ -455,67 +769,50 @@ impl Lattice {
             }
             point_group_vector
         };
-        (points_vector, point_group_vector)
+        Ok((points_vector, point_group_vector))
     }
 
     /// Returns a numbered list of ring locations.
     ///
-    /// # Panics
-    /// Can panic when `Lattice` was not created from file.
-    #[must_use]
-    pub fn no_rings_plot(&self) -> Vec<(usize, f32, f32)> {
-        let (points_vector, point_group_vector) = self.no_rings_detector();
+    /// # Errors
+    /// Returns an error when `Lattice` was not created from file.
+    pub fn no_rings_plot(&self) -> Result<Vec<(usize, f32, f32)>, LatticeError> {
+        let (points_vector, point_group_vector) = self.no_rings_detector()?;
         println!("points_vector = {points_vector:?}");
         println!("length: {}", points_vector.len());
         println!("point_group_vector = {point_group_vector:?}");
 
-        izip!(
+        Ok(izip!(
             point_group_vector,
             self.points.iter().map(|o| o.x),
             self.points.iter().map(|o| o.y)
         )
-        .collect_vec()
+        .collect_vec())
     }
 
     /// Create a `SiteFilter` to prevent small rings from forming in the material.
     ///
-    /// # Panics
-    /// Can panic when `Lattice` was not created from file.
-    #[must_use]
-    pub fn no_rings(&self) -> site_filter::SiteFilter {
-        let (_, point_group_vector) = self.no_rings_detector();
+    /// # Errors
+    /// Returns an error when `Lattice` was not created from file.
+    pub fn no_rings(&self) -> Result<site_filter::SiteFilter, LatticeError> {
+        let (_, point_group_vector) = self.no_rings_detector()?;
 
         let mut disabled_oxygens = vec![];
 
         for (number, oxygen) in self.oxygens.iter().enumerate() {
-            match oxygen.sitetype {
-                SiteType::Singlet(_) => {}
-                SiteType::Midpoint(p) => {
-                    let connections = p.0;
-                    let same_group = |a: usize, b: usize| {
-                        point_group_vector[connections[a].0] == point_group_vector[connections[b].0]
-                    };
-
-                    if same_group(0, 1) {
-                        disabled_oxygens.push(OxygenIndex(number));
-                    };
-                }
-                SiteType::Tripoint(p) => {
-                    let connections = p.0;
-                    let same_group = |a: usize, b: usize| {
-                        point_group_vector[connections[a].0] == point_group_vector[connections[b].0]
-                    };
-
-                    if same_group(0, 1) | same_group(1, 2) | same_group(0, 2) {
-                        disabled_oxygens.push(OxygenIndex(number));
-                    };
-                }
+            let same_group = oxygen
+                .sitetype
+                .iter()
+                .tuple_combinations()
+                .any(|(one, two)| point_group_vector[one.0] == point_group_vector[two.0]);
+            if same_group {
+                disabled_oxygens.push(OxygenIndex(number));
             }
         }
 
-        site_filter::SiteFilter {
+        Ok(site_filter::SiteFilter {
             wrapped: disabled_oxygens,
-        }
+        })
     }
 
     /// Returns the coordinates of the lattice points in two lists.
@@ -544,6 +841,21 @@ impl Lattice {
         (x_points, y_points)
     }
 
+    /// Returns the coordinates of the sites of the given kind in two lists.
+    /// Use with the * star operator in a `plt.plot` function:
+    ///
+    /// ```python
+    /// plt.plot(*solved_lattice.sites_to_plot("tripoint"), "o")
+    /// ```
+    #[must_use]
+    pub fn sites_to_plot(&self, kind: &SiteKind) -> (Vec<f32>, Vec<f32>) {
+        self.oxygens
+            .iter()
+            .filter(|p| p.sitetype.kind == *kind)
+            .map(|p| (p.x, p.y))
+            .unzip()
+    }
+
     /// Returns the coordinates of the tripoints in two lists.
     /// Use with the * star operator in a `plt.plot` function:
     ///
@@ -552,19 +864,7 @@ impl Lattice {
     /// ```
     #[must_use]
     pub fn tripoints_to_plot(&self) -> (Vec<f32>, Vec<f32>) {
-        let x_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Tripoint(_)))
-            .map(|p| p.x)
-            .collect_vec();
-        let y_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Tripoint(_)))
-            .map(|p| p.y)
-            .collect_vec();
-        (x_points, y_points)
+        self.sites_to_plot(&SiteKind::Tripoint)
     }
 
     /// Returns the coordinates of the tripoints in two lists.
@@ -575,19 +875,7 @@ impl Lattice {
     /// ```
     #[must_use]
     pub fn midpoints_to_plot(&self) -> (Vec<f32>, Vec<f32>) {
-        let x_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Midpoint(_)))
-            .map(|p| p.x)
-            .collect_vec();
-        let y_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Midpoint(_)))
-            .map(|p| p.y)
-            .collect_vec();
-        (x_points, y_points)
+        self.sites_to_plot(&SiteKind::Midpoint)
     }
 
     /// Returns the coordinates of the tripoints in two lists.
@@ -598,19 +886,7 @@ impl Lattice {
     /// ```
     #[must_use]
     pub fn singlets_to_plot(&self) -> (Vec<f32>, Vec<f32>) {
-        let x_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Singlet(_)))
-            .map(|p| p.x)
-            .collect_vec();
-        let y_points = self
-            .oxygens
-            .iter()
-            .filter(|p| matches!(p.sitetype, SiteType::Singlet(_)))
-            .map(|p| p.y)
-            .collect_vec();
-        (x_points, y_points)
+        self.sites_to_plot(&SiteKind::Singlet)
     }
 
     /// Generates a more efficient representation of the lattice
@@ -634,8 +910,75 @@ impl Lattice {
             points: self.points.clone(),
             oxygens: solved_oxygens,
             basis: self.basis,
+            pbc: self.pbc,
+            periodicity: self.periodicity.clone(),
             source_file: self.source_file.clone(),
+            attachment: self.attachment.clone(),
+            geometry: self.geometry.clone(),
+            criteria: self.criteria,
+            rejected: self.rejected.clone(),
+            kinds: self.kinds.clone(),
+            node_search_distance: self.node_search_distance,
+        }
+    }
+
+    /// A hash of the attachment points and the basis of the lattice, which is
+    /// the same on every machine and in every run. Coordinates are compared
+    /// to a thousandth of an ångström, and the order of the points does not
+    /// matter.
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        let mut points = self
+            .points
+            .iter()
+            .filter(|point| point.ghost_to.is_none())
+            .map(|point| [point.x, point.y, point.z])
+            .collect_vec();
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mut hasher = StableHasher::new();
+        for coordinate in points.into_iter().flatten() {
+            hasher.write_coordinate(coordinate);
+        }
+        for coordinate in self.basis.iter().flatten().flatten() {
+            hasher.write_coordinate(*coordinate);
+        }
+        hasher.write(&self.pbc.map(u8::from));
+        hasher.finish()
+    }
+
+    /// A stable identifier of `solution`, derived from the `fingerprint` of
+    /// the lattice and the kinds and positions of the occupied sites. It
+    /// stays the same across reruns and machines, so it can be used to name
+    /// files and to compare results.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::lattice_points, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(lattice_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// let solutions = intermediary.solve(true, true);
+    /// let id = lattice.solution_id(&solutions[0]);
+    /// assert_eq!(id.len(), 16);
+    /// assert_eq!(id, lattice.solution_id(&intermediary.solve_parallel(true, true)[0]));
+    /// assert_ne!(id, lattice.solution_id(&solutions[1]));
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    #[must_use]
+    pub fn solution_id(&self, solution: &BitArraySolution) -> String {
+        let mut sites = solution
+            .0
+            .ones()
+            .filter_map(|index| self.oxygens.get(index))
+            .map(|oxygen| (oxygen.sitetype.kind.name(), [oxygen.x, oxygen.y, oxygen.z]))
+            .collect_vec();
+        sites.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mut hasher = StableHasher::from_hash(self.fingerprint());
+        for (kind, position) in sites {
+            hasher.write(kind.as_bytes());
+            for coordinate in position {
+                hasher.write_coordinate(coordinate);
+            }
         }
+        format!("{:016x}", hasher.finish())
     }
 
     /// Export the solved lattice to a json file.
@@ -643,9 +986,9 @@ impl Lattice {
     /// - path must be a valid path name.
     /// - name should end with ".json".
     ///
-    /// # Panics
-    /// Can panic when path is invalid.
-    pub fn export(&self, path: &OsString, name: String) {
+    /// # Errors
+    /// Returns an error when path is invalid.
+    pub fn export(&self, path: &OsString, name: String) -> Result<(), LatticeError> {
         let mut data = json::JsonValue::new_object();
         {
             let mut points = vec![];
@@ -659,108 +1002,107 @@ impl Lattice {
             data["lattice_points"] = points.into();
         }
 
-        {
-            let mut tripoints = vec![];
-            let mut midpoints = vec![];
-            let mut singles = vec![];
-            for oxygen in &self.oxygens {
+        for kind in &self.kinds {
+            let mut sites = vec![];
+            for oxygen in self.oxygens.iter().filter(|o| o.sitetype.kind == *kind) {
                 let mut new_obj = json::JsonValue::new_object();
                 new_obj["x"] = oxygen.x.into();
                 new_obj["y"] = oxygen.y.into();
-
-                match oxygen.sitetype {
-                    SiteType::Tripoint(_) => tripoints.push(new_obj),
-                    SiteType::Midpoint(_) => midpoints.push(new_obj),
-                    SiteType::Singlet(_) => singles.push(new_obj),
-                };
+                sites.push(new_obj);
             }
-            data["tripoints"] = tripoints.into();
-            data["midpoints"] = midpoints.into();
-            data["singles"] = singles.into();
+            // The plotting scripts expect "singles" for the singlets.
+            let key = match kind {
+                SiteKind::Singlet => "singles".into(),
+                kind => format!("{}s", kind.name()),
+            };
+            data[key.as_str()] = sites.into();
         }
 
         let mut filename = path.clone();
         filename.push("/");
         filename.push(name);
 
-        let mut file = File::create(&filename).unwrap_or_else(|_| panic!("{:?}", &filename));
+        let mut file = File::create(&filename)?;
 
-        file.write_all(data.pretty(4).as_bytes()).unwrap();
+        file.write_all(data.pretty(4).as_bytes())?;
+        Ok(())
     }
 
     /// Export the found solution as a json file, which can be converted to other
     /// formats with ASE.
     ///
-    /// # Panics
-    /// Can panic when `Lattice` was not created from file or filename points to
+    /// # Errors
+    /// Returns an error when `Lattice` was not created from file or filename points to
     /// invalid path.
-    pub fn export_as_ase_json(&self, filename: &String) {
-        let parsed = self.source_file.as_ref().unwrap();
+    pub fn export_as_ase_json(&self, filename: &String) -> Result<(), LatticeError> {
+        let parsed = self.source_file.as_ref().ok_or(LatticeError::NotFromFile)?;
         let oxygens = &self.oxygens;
-        let last_id = &parsed["ids"][parsed["ids"].len() - 1].to_string();
-
-        let old_numbers = parsed[last_id]["numbers"].clone();
-        let mut new_numbers = parsed[last_id]["numbers"].clone();
-        let mut new_positions = parsed[last_id]["positions"].clone();
+        let structure = ase::last_structure(parsed)?;
+        // Also validates the arrays which are edited below.
+        let markers = self.attachment.select_in(structure)?;
 
-        let z_coords = new_positions["__ndarray__"][2].members().skip(2).step_by(3);
+        let mut new_numbers = structure["numbers"].clone();
+        let mut new_positions = structure["positions"].clone();
 
-        new_numbers["__ndarray__"][2].clear();
-        for (old_number, z) in zip(old_numbers["__ndarray__"][2].members(), z_coords) {
-            if (old_number.as_usize() == Some(1usize)) & (z.as_f64() < Some(20.0)) {
-                new_numbers["__ndarray__"][2]
-                    .push(8)
-                    .expect("Pushing new number failed.");
-            } else {
-                new_numbers["__ndarray__"][2]
-                    .push(old_number.clone())
-                    .expect("Pushihng old number failed");
-            }
+        for marker in markers {
+            new_numbers["__ndarray__"][2][marker] = self.geometry.bridge_element.into();
         }
+        let first_crown_atom = new_numbers["__ndarray__"][2].len();
 
-        let mut c = (0usize, 0usize, 0usize);
         for oxygen in oxygens {
-            new_numbers["__ndarray__"][2]
-                .push(14)
-                .expect("new_numbers[\"__ndarray__\"][2].push(8)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.x)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.x)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.y)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.y)");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.z)
-                .expect("new_positions[\"__ndarray__\"][2].push(oxygen.z)");
-            match oxygen.sitetype {
-                SiteType::Tripoint(_) => c = (c.0 + 1, c.1, c.2),
-                SiteType::Midpoint(_) => c = (c.0, c.1 + 1, c.2),
-                SiteType::Singlet(_) => c = (c.0, c.1, c.2 + 1),
-            };
-            self.add_crown(oxygen, &mut new_numbers, &mut new_positions);
+            new_numbers["__ndarray__"][2].push(self.geometry.site_element)?;
+            new_positions["__ndarray__"][2].push(oxygen.x)?;
+            new_positions["__ndarray__"][2].push(oxygen.y)?;
+            new_positions["__ndarray__"][2].push(oxygen.z)?;
+            self.add_crown(oxygen, &mut new_numbers, &mut new_positions)?;
         }
 
-        self.merge_conflicting_sites(&mut new_numbers, &mut new_positions);
+        self.merge_conflicting_sites(&mut new_numbers, &mut new_positions, first_crown_atom)?;
         new_numbers["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
         new_positions["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
 
         let mut export_data = json::JsonValue::new_object();
         export_data["1"] = object! {
-            cell: parsed[last_id]["cell"].clone(),
-            ctime: parsed[last_id]["ctime"].clone(),
-            mtime: parsed[last_id]["mtime"].clone(),
+            cell: structure["cell"].clone(),
+            ctime: structure["ctime"].clone(),
+            mtime: structure["mtime"].clone(),
             numbers: new_numbers,
-            pbc: parsed[last_id]["pbc"].clone(),
+            pbc: structure["pbc"].clone(),
             positions: new_positions,
             unique_id: "Not unique",
-            user: parsed[last_id]["user"].clone(),
-            tripoints: c.0,
-            midpoints: c.1,
-            singlets: c.2,
+            user: structure["user"].clone(),
         };
+        for kind in &self.kinds {
+            export_data["1"][format!("{}s", kind.name()).as_str()] = oxygens
+                .iter()
+                .filter(|o| o.sitetype.kind == *kind)
+                .count()
+                .into();
+        }
+
+        let mut file = File::create(filename)?;
+        file.write_all(export_data.pretty(4).as_bytes())?;
+        Ok(())
+    }
 
-        let mut file = File::create(filename).expect("Folder does not exist!");
-        file.write_all(export_data.pretty(4).as_bytes()).unwrap();
+    /// The positions of the crown atoms of `oxygen`.
+    fn crown_positions(&self, oxygen: &Oxygen) -> Vec<[f32; 3]> {
+        let reference = match oxygen.sitetype.as_slice() {
+            [one, two] => {
+                let theta = double_angle(&self.points[one.0], &self.points[two.0]);
+                [theta.cos(), theta.sin(), 0.0]
+            }
+            _ => [1.0, 0.0, 0.0],
+        };
+        self.geometry
+            .crown(
+                &oxygen.sitetype.kind,
+                self.site_normal(oxygen.sitetype.as_slice()),
+                reference,
+            )
+            .into_iter()
+            .map(|offset| vector::add(oxygen.position(), offset))
+            .collect()
     }
 
     fn add_crown(
@@ -768,41 +1110,25 @@ impl Lattice {
         oxygen: &Oxygen,
         new_numbers: &mut JsonValue,
         new_positions: &mut JsonValue,
-    ) {
-        let double_crown;
-        let single_crown;
-        let new_crown = match oxygen.sitetype {
-            SiteType::Singlet(_) => {
-                single_crown = triple_crown_rotated(0.0);
-                single_crown.iter()
-            }
-            SiteType::Midpoint(p) => {
-                double_crown = double_crown_rotated(double_angle(
-                    &self.points[p.0[0].0],
-                    &self.points[p.0[1].0],
-                ));
-                double_crown.iter()
-            }
-            SiteType::Tripoint(_) => SINGLE_CROWN.iter(),
-        };
-
-        for (x, y, z) in new_crown.tuples() {
-            new_numbers["__ndarray__"][2]
-                .push(1)
-                .expect("Adding to borrowed new_numbers failed");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.x + x)
-                .expect("Adding to borrowed new_positions failed");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.y + y)
-                .expect("Adding to borrowed new_positions failed");
-            new_positions["__ndarray__"][2]
-                .push(oxygen.z + z)
-                .expect("Adding to borrowed new_positions failed");
+    ) -> Result<(), LatticeError> {
+        for [x, y, z] in self.crown_positions(oxygen) {
+            new_numbers["__ndarray__"][2].push(self.geometry.crown_element)?;
+            new_positions["__ndarray__"][2].push(x)?;
+            new_positions["__ndarray__"][2].push(y)?;
+            new_positions["__ndarray__"][2].push(z)?;
         }
+        Ok(())
     }
 
-    fn merge_conflicting_sites(&self, numbers: &mut JsonValue, positions: &mut JsonValue) {
+    /// Replaces pairs of crown atoms which overlap with a single bridge atom.
+    /// Only the atoms starting at `first_crown_atom` are considered, so the
+    /// atoms of the source structure are never merged.
+    fn merge_conflicting_sites(
+        &self,
+        numbers: &mut JsonValue,
+        positions: &mut JsonValue,
+        first_crown_atom: usize,
+    ) -> Result<(), LatticeError> {
         let mut hydrogen_positions: Vec<(usize, [f32; 3])> = vec![];
 
         let mut new_numbers = numbers.clone();
@@ -810,6 +1136,7 @@ impl Lattice {
         let mut new_positions = positions.clone();
         new_positions["__ndarray__"][2].clear();
 
+        let malformed = || LatticeError::MalformedArray("positions".into());
         let zipped = zip(
             numbers["__ndarray__"][2].members(),
             positions["__ndarray__"][2]
@@ -818,51 +1145,46 @@ impl Lattice {
         )
         .collect_vec();
 
-        'outer: for (number, location) in zipped {
-            if number.as_i64().unwrap() == 1 && location.2.as_f32().unwrap() < 20.0 {
-                let coords = [
-                    location.0.as_f32().unwrap(),
-                    location.1.as_f32().unwrap(),
-                    location.2.as_f32().unwrap(),
-                ];
+        'outer: for (index, (number, location)) in zipped.into_iter().enumerate() {
+            let number = number
+                .as_i64()
+                .ok_or_else(|| LatticeError::MalformedArray("numbers".into()))?;
+            let coords = [
+                location.0.as_f32().ok_or_else(malformed)?,
+                location.1.as_f32().ok_or_else(malformed)?,
+                location.2.as_f32().ok_or_else(malformed)?,
+            ];
+            if number == self.geometry.crown_element && index >= first_crown_atom {
                 for (h_num, hydrogen) in hydrogen_positions.iter().enumerate() {
-                    if self.distance_sq(&coords, &hydrogen.1) < H_MARGIN {
-                        new_numbers["__ndarray__"][2][hydrogen.0] = 8.into();
+                    if self.distance_sq(&coords, &hydrogen.1) < self.geometry.merge_distance.powi(2)
+                    {
+                        new_numbers["__ndarray__"][2][hydrogen.0] =
+                            self.geometry.bridge_element.into();
                         hydrogen_positions.remove(h_num);
                         continue 'outer;
                     }
                 }
-                hydrogen_positions.push((
-                    new_numbers["__ndarray__"][2].len(),
-                    [coords[0], coords[1], coords[2]],
-                ));
+                hydrogen_positions.push((new_numbers["__ndarray__"][2].len(), coords));
             }
 
-            new_numbers["__ndarray__"][2]
-                .push(number.as_i64().unwrap())
-                .expect("Adding to borrowed new_numbers failed");
-            new_positions["__ndarray__"][2]
-                .push(location.0.clone())
-                .expect("Adding to borrowed new_positions failed");
-            new_positions["__ndarray__"][2]
-                .push(location.1.clone())
-                .expect("Adding to borrowed new_positions failed");
-            new_positions["__ndarray__"][2]
-                .push(location.2.clone())
-                .expect("Adding to borrowed new_positions failed");
+            new_numbers["__ndarray__"][2].push(number)?;
+            new_positions["__ndarray__"][2].push(location.0.clone())?;
+            new_positions["__ndarray__"][2].push(location.1.clone())?;
+            new_positions["__ndarray__"][2].push(location.2.clone())?;
         }
         new_numbers["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
         new_positions["__ndarray__"][0][0] = new_numbers["__ndarray__"][2].len().into();
         *numbers = new_numbers;
         *positions = new_positions;
+        Ok(())
     }
 }
 
-fn create_silicon_lattice(lattice_3d: Vec<(Vec<f32>, Vec<Vec<f32>>)>) -> Lattice {
+fn create_silicon_lattice(lattice_3d: Vec<InputPoint>, bonds: &[[f32; 3]]) -> Lattice {
     let mut out_lattice = Lattice::new();
 
-    for (location, ghosts) in lattice_3d {
-        let new_point = LatticePoint::new(location[0], location[1], location[2], None);
+    for ((location, ghosts), bond) in zip(lattice_3d, bonds) {
+        let new_point = LatticePoint::new(location[0], location[1], location[2], *bond, None);
         out_lattice.add_point(new_point.clone());
 
         for ghost in ghosts {
@@ -870,6 +1192,7 @@ fn create_silicon_lattice(lattice_3d: Vec<(Vec<f32>, Vec<Vec<f32>>)>) -> Lattice
                 ghost[0],
                 ghost[1],
                 ghost[2],
+                *bond,
                 Some(new_point.clone()),
             ));
         }
@@ -880,8 +1203,21 @@ fn create_silicon_lattice(lattice_3d: Vec<(Vec<f32>, Vec<Vec<f32>>)>) -> Lattice
     out_lattice
 }
 
-fn turn_2d_3d(input_lattice: Vec<(Vec<f32>, Vec<Vec<f32>>)>) -> Vec<(Vec<f32>, Vec<Vec<f32>>)> {
-    if input_lattice[0].0.len() == 2 {
+fn turn_2d_3d(input_lattice: Vec<InputPoint>) -> Result<Vec<InputPoint>, LatticeError> {
+    let dimension = input_lattice
+        .first()
+        .ok_or(LatticeError::NoAttachmentSites)?
+        .0
+        .len();
+    if let Some(wrong) = input_lattice
+        .iter()
+        .flat_map(|(point, ghosts)| std::iter::once(point).chain(ghosts))
+        .map(Vec::len)
+        .find(|&len| len != dimension)
+    {
+        return Err(LatticeError::PointDimension(wrong));
+    }
+    if dimension == 2 {
         let mut new_lattice = vec![];
         for (point, ghosts) in input_lattice {
             let mut new_point = point.clone();
@@ -892,10 +1228,10 @@ fn turn_2d_3d(input_lattice: Vec<(Vec<f32>, Vec<Vec<f32>>)>) -> Vec<(Vec<f32>, V
             }
             new_lattice.push((new_point, new_ghosts));
         }
-        new_lattice
-    } else if input_lattice[0].0.len() == 3 {
-        input_lattice
+        Ok(new_lattice)
+    } else if dimension == 3 {
+        Ok(input_lattice)
     } else {
-        panic!("Input lattice layout is incorrect: points must be two or threedimentional.")
+        Err(LatticeError::PointDimension(dimension))
     }
 }
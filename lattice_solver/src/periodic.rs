@@ -0,0 +1,266 @@
+//! Minimum-image distances between points of a periodic lattice.
+use itertools::Itertools;
+use json::{object, JsonValue};
+use kiddo::{float::kdtree::KdTree, SquaredEuclidean};
+
+use crate::BINSIZE;
+
+/// The in-plane periodicity of a `Lattice`, used to find the shortest distance
+/// between two points across the cell boundaries.
+#[derive(Clone, Default)]
+pub enum Periodicity {
+    /// A cell spanned by two lattice vectors, which can be non-orthogonal.
+    Cell(PeriodicCell),
+    /// A cell which is only periodic along the given lattice vector.
+    Line([f32; 3]),
+    /// A cell without periodic directions.
+    #[default]
+    Open,
+    /// A rectangular box of the given width and height, for lattices without
+    /// a basis.
+    Rectangular(f32, f32),
+}
+
+impl Periodicity {
+    /// Squared minimum-image distance between `one` and `two`.
+    #[allow(clippy::suboptimal_flops)]
+    pub fn distance_sq(&self, one: &[f32; 3], two: &[f32; 3]) -> f32 {
+        match self {
+            Self::Cell(cell) => {
+                let delta = cell.minimum_image([two[0] - one[0], two[1] - one[1], two[2] - one[2]]);
+                delta[0].powi(2) + delta[1].powi(2) + delta[2].powi(2)
+            }
+            Self::Line(vector) => {
+                let mut delta = [two[0] - one[0], two[1] - one[1], two[2] - one[2]];
+                let length_sq = |v: &[f32; 3]| v[0].mul_add(v[0], v[1].mul_add(v[1], v[2] * v[2]));
+                let along =
+                    vector[0].mul_add(delta[0], vector[1].mul_add(delta[1], vector[2] * delta[2]));
+                let steps = (along / length_sq(vector)).round();
+                for (d, v) in delta.iter_mut().zip(vector) {
+                    *d = steps.mul_add(-v, *d);
+                }
+                length_sq(&delta)
+            }
+            Self::Open => {
+                (one[0] - two[0]).powi(2) + (one[1] - two[1]).powi(2) + (one[2] - two[2]).powi(2)
+            }
+            Self::Rectangular(max_x, max_y) => {
+                let wrap = |d: f32, max: f32| if d > max / 2.0 { d - max } else { d };
+                let delta_x = wrap((one[0] - two[0]).abs(), *max_x);
+                let delta_y = wrap((one[1] - two[1]).abs(), *max_y);
+                delta_x.powi(2) + delta_y.powi(2) + (one[2] - two[2]).powi(2)
+            }
+        }
+    }
+
+    /// Minimum-image distance between `one` and `two`.
+    pub fn distance(&self, one: &[f32; 3], two: &[f32; 3]) -> f32 {
+        self.distance_sq(one, two).sqrt()
+    }
+
+    /// The periodic image of `position` inside the cell, which is the same for
+    /// all images of a position.
+    pub fn wrap(&self, position: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Cell(cell) => cell.wrap(position),
+            Self::Line(vector) => {
+                let length_sq = vector[0].mul_add(
+                    vector[0],
+                    vector[1].mul_add(vector[1], vector[2] * vector[2]),
+                );
+                let along = vector[0].mul_add(
+                    position[0],
+                    vector[1].mul_add(position[1], vector[2] * position[2]),
+                );
+                let steps = (along / length_sq).floor();
+                [
+                    steps.mul_add(-vector[0], position[0]),
+                    steps.mul_add(-vector[1], position[1]),
+                    steps.mul_add(-vector[2], position[2]),
+                ]
+            }
+            Self::Open => position,
+            Self::Rectangular(max_x, max_y) => {
+                // A box without width is not periodic in that direction.
+                let wrap = |value: f32, max: f32| {
+                    if max > 0.0 {
+                        value.rem_euclid(max)
+                    } else {
+                        value
+                    }
+                };
+                [
+                    wrap(position[0], *max_x),
+                    wrap(position[1], *max_y),
+                    position[2],
+                ]
+            }
+        }
+    }
+
+    /// Serialize the periodicity, see `BitArrayRepresentation::to_json`.
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            Self::Cell(cell) => object! { kind: "cell", a: cell.a.to_vec(), b: cell.b.to_vec() },
+            Self::Line(vector) => object! { kind: "line", vector: vector.to_vec() },
+            Self::Open => object! { kind: "open" },
+            Self::Rectangular(max_x, max_y) => {
+                object! { kind: "rectangular", max_x: *max_x, max_y: *max_y }
+            }
+        }
+    }
+
+    /// Deserialize a periodicity written by `Periodicity::to_json`.
+    pub fn from_json(data: &JsonValue) -> Option<Self> {
+        let float = |value: &JsonValue| value.as_f64().map(|value| value as f32);
+        let vector = |value: &JsonValue| match value.members().map(float).collect_vec()[..] {
+            [Some(x), Some(y), Some(z)] => Some([x, y, z]),
+            _ => None,
+        };
+        match data["kind"].as_str()? {
+            "cell" => PeriodicCell::new(&[vector(&data["a"])?, vector(&data["b"])?, [0.0; 3]])
+                .map(Self::Cell),
+            "line" => Some(Self::Line(vector(&data["vector"])?)),
+            "open" => Some(Self::Open),
+            "rectangular" => Some(Self::Rectangular(
+                float(&data["max_x"])?,
+                float(&data["max_y"])?,
+            )),
+            _ => None,
+        }
+    }
+
+    /// The translations to the periodic images in the neighbouring cells,
+    /// including the cell itself.
+    fn image_offsets(&self) -> Vec<[f32; 3]> {
+        let steps = [-1.0f32, 0.0, 1.0];
+        match self {
+            Self::Cell(cell) => steps
+                .iter()
+                .cartesian_product(steps)
+                .map(|(n, m)| {
+                    [
+                        n.mul_add(cell.a[0], m * cell.b[0]),
+                        n.mul_add(cell.a[1], m * cell.b[1]),
+                        n.mul_add(cell.a[2], m * cell.b[2]),
+                    ]
+                })
+                .collect(),
+            Self::Line(vector) => steps.iter().map(|n| vector.map(|v| n * v)).collect(),
+            Self::Open => vec![[0.0; 3]],
+            Self::Rectangular(max_x, max_y) => steps
+                .iter()
+                .cartesian_product(steps)
+                .map(|(n, m)| [n * max_x, m * max_y, 0.0])
+                .collect(),
+        }
+    }
+
+    /// For every position, the other positions within `radius`, in ascending
+    /// order. The positions are searched with a kd-tree in the neighbouring
+    /// periodic images only, so `radius` should be smaller than half the
+    /// width of the cell.
+    pub fn neighbours(&self, positions: &[[f32; 3]], radius: f32) -> Vec<Vec<usize>> {
+        let kdtree: KdTree<_, u64, 3, BINSIZE, u32> = KdTree::from(&positions.to_vec());
+        let offsets = self.image_offsets();
+        positions
+            .iter()
+            .enumerate()
+            .map(|(number, position)| {
+                offsets
+                    .iter()
+                    .flat_map(|offset| {
+                        let image = [
+                            position[0] + offset[0],
+                            position[1] + offset[1],
+                            position[2] + offset[2],
+                        ];
+                        kdtree.within_unsorted::<SquaredEuclidean>(&image, radius.powi(2))
+                    })
+                    .map(|neighbour| neighbour.item as usize)
+                    .filter(|&other| {
+                        other != number
+                            && self.distance_sq(position, &positions[other]) < radius.powi(2)
+                    })
+                    .sorted_unstable()
+                    .dedup()
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A cell which is periodic along its first two lattice vectors. The third
+/// vector is assumed to be out of plane and is not wrapped.
+#[derive(Clone)]
+pub struct PeriodicCell {
+    a: [f32; 3],
+    b: [f32; 3],
+    /// Inverse of the in-plane components of `a` and `b`, used to find the
+    /// fractional coordinates of a vector.
+    inverse: [[f32; 2]; 2],
+}
+
+impl PeriodicCell {
+    /// Returns `None` when the in-plane components of `a` and `b` do not span
+    /// a plane, for example for the flat basis of a `Lattice` without cell.
+    pub fn new(basis: &[[f32; 3]; 3]) -> Option<Self> {
+        let [a, b, _] = *basis;
+        let determinant = a[0].mul_add(b[1], -(a[1] * b[0]));
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(Self {
+            a,
+            b,
+            inverse: [
+                [b[1] / determinant, -b[0] / determinant],
+                [-a[1] / determinant, a[0] / determinant],
+            ],
+        })
+    }
+
+    /// Shifts `position` by a whole number of lattice vectors, such that its
+    /// fractional coordinates are in `[0, 1)`.
+    pub fn wrap(&self, position: [f32; 3]) -> [f32; 3] {
+        let frac_a = self.inverse[0][0].mul_add(position[0], self.inverse[0][1] * position[1]);
+        let frac_b = self.inverse[1][0].mul_add(position[0], self.inverse[1][1] * position[1]);
+        let (n, m) = (frac_a.floor(), frac_b.floor());
+        [
+            n.mul_add(-self.a[0], m.mul_add(-self.b[0], position[0])),
+            n.mul_add(-self.a[1], m.mul_add(-self.b[1], position[1])),
+            n.mul_add(-self.a[2], m.mul_add(-self.b[2], position[2])),
+        ]
+    }
+
+    /// Shifts `delta` by a whole number of lattice vectors, such that it
+    /// becomes as short as possible.
+    ///
+    /// Rounding the fractional coordinates gives the shortest vector for
+    /// rectangular cells only, so the neighbouring images are checked as well.
+    pub fn minimum_image(&self, delta: [f32; 3]) -> [f32; 3] {
+        let frac_a = self.inverse[0][0].mul_add(delta[0], self.inverse[0][1] * delta[1]);
+        let frac_b = self.inverse[1][0].mul_add(delta[0], self.inverse[1][1] * delta[1]);
+        let (base_a, base_b) = (frac_a.round(), frac_b.round());
+
+        let shifted = |n: f32, m: f32| {
+            [
+                n.mul_add(-self.a[0], m.mul_add(-self.b[0], delta[0])),
+                n.mul_add(-self.a[1], m.mul_add(-self.b[1], delta[1])),
+                n.mul_add(-self.a[2], m.mul_add(-self.b[2], delta[2])),
+            ]
+        };
+        let length_sq = |v: &[f32; 3]| v[0].mul_add(v[0], v[1].mul_add(v[1], v[2] * v[2]));
+
+        let mut best = shifted(base_a, base_b);
+        for n in [-1.0, 0.0, 1.0] {
+            for m in [-1.0, 0.0, 1.0] {
+                let candidate = shifted(base_a + n, base_b + m);
+                if length_sq(&candidate) < length_sq(&best) {
+                    best = candidate;
+                }
+            }
+        }
+        best
+    }
+}
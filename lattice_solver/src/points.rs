@@ -1,26 +1,37 @@
 use std::sync::{Arc, RwLock};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use crate::SiteKind;
+
+/// The index of a candidate site of a `Lattice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct OxygenIndex(pub usize);
 
-#[derive(Clone, Copy, Debug)]
+/// The index of an attachment point of a `Lattice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct LatticeIndex(pub usize);
 
+/// The bond direction of points for which the bonded atom is unknown.
+pub const DOWN: [f32; 3] = [0.0, 0.0, -1.0];
+
 #[derive(Debug)]
 pub struct LatticePoint {
     pub x: f32,
     pub y: f32,
     pub z: f32,
+    /// Unit vector from the substrate atom to this point, along which new
+    /// atoms are attached.
+    pub bond: [f32; 3],
     pub connected_to: RwLock<Vec<OxygenIndex>>,
-    pub ghost_to: Option<Arc<LatticePoint>>,
+    pub ghost_to: Option<Arc<Self>>,
 }
 
 impl LatticePoint {
-    pub fn new(x: f32, y: f32, z: f32, ghost_to: Option<Arc<Self>>) -> Arc<Self> {
+    pub fn new(x: f32, y: f32, z: f32, bond: [f32; 3], ghost_to: Option<Arc<Self>>) -> Arc<Self> {
         Arc::new(Self {
             x,
             y,
             z,
+            bond,
             connected_to: RwLock::new(vec![]),
             ghost_to,
         })
@@ -57,30 +68,29 @@ impl Oxygen {
             exclusions: vec![],
         }
     }
+
+    pub const fn position(&self) -> [f32; 3] {
+        [self.x, self.y, self.z]
+    }
 }
 
-#[derive(Clone, Copy)]
-pub enum SiteType {
-    Singlet(Singlet),
-    Midpoint(Midpoint),
-    Tripoint(Tripoint),
+/// The kind of a candidate site, and the attachment points it connects.
+#[derive(Clone)]
+pub struct SiteType {
+    pub kind: SiteKind,
+    pub points: Vec<LatticeIndex>,
 }
 
 impl SiteType {
-    pub fn iter(&self) -> std::slice::Iter<'_, LatticeIndex> {
-        match self {
-            Self::Tripoint(c) => c.0.iter(),
-            Self::Midpoint(c) => c.0.iter(),
-            Self::Singlet(c) => c.0.iter(),
-        }
+    pub const fn new(kind: SiteKind, points: Vec<LatticeIndex>) -> Self {
+        Self { kind, points }
     }
-}
-
-#[derive(Clone, Copy)]
-pub struct Singlet(pub [LatticeIndex; 1]);
 
-#[derive(Clone, Copy)]
-pub struct Midpoint(pub [LatticeIndex; 2]);
+    pub fn iter(&self) -> std::slice::Iter<'_, LatticeIndex> {
+        self.points.iter()
+    }
 
-#[derive(Clone, Copy)]
-pub struct Tripoint(pub [LatticeIndex; 3]);
+    pub fn as_slice(&self) -> &[LatticeIndex] {
+        &self.points
+    }
+}
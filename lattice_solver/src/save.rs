@@ -0,0 +1,758 @@
+//! Complete, versioned json serialization of `Lattice`, `BitArrayRepresentation`
+//! and `BitArraySettings`. The checkpoints of a solve are written with the
+//! same helpers.
+//!
+//! Unlike `Lattice::export`, which is meant for plotting, these files contain
+//! everything needed to continue working with a lattice without rebuilding it.
+use fixedbitset::FixedBitSet;
+use itertools::Itertools;
+use json::{object, JsonValue};
+use std::{fs, fs::File, io::prelude::*, path::Path, sync::Arc};
+
+use crate::{criteria::Window, *};
+
+/// Version of the save format written by this version of crystacean. Files of
+/// older versions can still be read.
+pub const SAVE_VERSION: usize = 4;
+
+pub fn invalid(what: &str) -> LatticeError {
+    LatticeError::InvalidSave(what.into())
+}
+
+fn read_file(filename: &str) -> Result<JsonValue, LatticeError> {
+    let mut buffer = String::new();
+    File::open(filename)?.read_to_string(&mut buffer)?;
+    Ok(json::parse(&buffer)?)
+}
+
+fn write_file(filename: &str, data: &JsonValue) -> Result<(), LatticeError> {
+    let mut file = File::create(filename)?;
+    file.write_all(data.dump().as_bytes())?;
+    Ok(())
+}
+
+/// Write to a temporary file first, so an interrupted write leaves the
+/// previous file intact.
+pub fn replace_file(path: &Path, data: &JsonValue) -> Result<(), LatticeError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let mut file = File::create(&temporary)?;
+    file.write_all(data.dump().as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+pub fn header(kind: &str) -> JsonValue {
+    object! { format: kind, version: SAVE_VERSION }
+}
+
+/// Checks the format written by `header`, and returns its version.
+pub fn check_header(data: &JsonValue, kind: &str) -> Result<usize, LatticeError> {
+    if data["format"].as_str() != Some(kind) {
+        return Err(invalid(&format!("not a saved {kind}")));
+    }
+    match data["version"].as_usize() {
+        Some(version @ 1..=SAVE_VERSION) => Ok(version),
+        Some(version) => Err(LatticeError::UnsupportedVersion(version)),
+        None => Err(invalid("version")),
+    }
+}
+
+/// `JsonValue::as_f32` does not round trip, so floats are read as `f64`.
+fn as_f32(value: &JsonValue) -> Option<f32> {
+    value.as_f64().map(|value| value as f32)
+}
+
+fn get_f32(data: &JsonValue, key: &str) -> Result<f32, LatticeError> {
+    as_f32(&data[key]).ok_or_else(|| invalid(key))
+}
+
+pub fn get_usize(data: &JsonValue, key: &str) -> Result<usize, LatticeError> {
+    data[key].as_usize().ok_or_else(|| invalid(key))
+}
+
+/// Infinite values can not be stored in json, so they are written as `null`.
+fn optional_f32(value: f32) -> JsonValue {
+    if value.is_finite() {
+        value.into()
+    } else {
+        JsonValue::Null
+    }
+}
+
+pub fn indices_from_json(data: &JsonValue, what: &str) -> Result<Vec<usize>, LatticeError> {
+    if !data.is_array() {
+        return Err(invalid(what));
+    }
+    data.members()
+        .map(JsonValue::as_usize)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid(what))
+}
+
+pub fn floats_from_json(data: &JsonValue, what: &str) -> Result<Vec<f32>, LatticeError> {
+    if !data.is_array() {
+        return Err(invalid(what));
+    }
+    data.members()
+        .map(as_f32)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid(what))
+}
+
+fn kinds_from_json(data: &JsonValue) -> Result<Vec<SiteKind>, LatticeError> {
+    if !data.is_array() {
+        return Err(invalid("kinds"));
+    }
+    data.members()
+        .map(|kind| kind.as_str().map(SiteKind::from_name))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| invalid("kinds"))
+}
+
+pub fn bitset_to_json(bitset: &FixedBitSet) -> JsonValue {
+    object! {
+        len: bitset.len(),
+        ones: bitset.ones().collect_vec(),
+    }
+}
+
+pub fn bitset_from_json(data: &JsonValue, what: &str) -> Result<FixedBitSet, LatticeError> {
+    let len = get_usize(data, "len").map_err(|_| invalid(what))?;
+    let mut bitset = FixedBitSet::with_capacity(len);
+    for one in indices_from_json(&data["ones"], what)? {
+        if one >= len {
+            return Err(invalid(what));
+        }
+        bitset.insert(one);
+    }
+    Ok(bitset)
+}
+
+/// A list of bitsets of length `len`, written as the indices of their ones.
+pub fn candidates_from_json(
+    data: &JsonValue,
+    len: usize,
+    what: &str,
+) -> Result<Vec<FixedBitSet>, LatticeError> {
+    if !data.is_array() {
+        return Err(invalid(what));
+    }
+    data.members()
+        .map(|ones| {
+            let mut bitset = FixedBitSet::with_capacity(len);
+            for one in indices_from_json(ones, what)? {
+                if one >= len {
+                    return Err(invalid(what));
+                }
+                bitset.insert(one);
+            }
+            Ok(bitset)
+        })
+        .collect()
+}
+
+pub fn candidates_to_json(candidates: &[FixedBitSet]) -> JsonValue {
+    candidates
+        .iter()
+        .map(|candidate| candidate.ones().collect_vec())
+        .collect_vec()
+        .into()
+}
+
+impl AttachmentSpec {
+    fn to_json(&self) -> JsonValue {
+        let region = match self.region {
+            MarkerRegion::Window(min, max) => object! {
+                kind: "window",
+                min: optional_f32(min),
+                max: optional_f32(max),
+            },
+            MarkerRegion::Top(depth) => object! { kind: "top", depth: depth },
+            MarkerRegion::Bottom(depth) => object! { kind: "bottom", depth: depth },
+        };
+        object! {
+            element: self.element,
+            region: region,
+            indices: self.indices.clone(),
+            tag: self.tag,
+        }
+    }
+
+    fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        let region = &data["region"];
+        let region = match region["kind"].as_str() {
+            Some("window") => MarkerRegion::Window(
+                as_f32(&region["min"]).unwrap_or(f32::NEG_INFINITY),
+                as_f32(&region["max"]).unwrap_or(f32::INFINITY),
+            ),
+            Some("top") => MarkerRegion::Top(get_f32(region, "depth")?),
+            Some("bottom") => MarkerRegion::Bottom(get_f32(region, "depth")?),
+            _ => return Err(invalid("attachment region")),
+        };
+        Ok(Self {
+            element: data["element"]
+                .as_i64()
+                .ok_or_else(|| invalid("attachment element"))?,
+            region,
+            indices: if data["indices"].is_null() {
+                None
+            } else {
+                Some(indices_from_json(&data["indices"], "attachment indices")?)
+            },
+            tag: data["tag"].as_i64(),
+        })
+    }
+}
+
+impl SiteCriteria {
+    fn to_json(self) -> JsonValue {
+        let window = |window: Window| {
+            object! { min: optional_f32(window.min), max: optional_f32(window.max) }
+        };
+        object! {
+            midpoint_distance: window(self.midpoint_distance),
+            tripoint_distance: window(self.tripoint_distance),
+            tripoint_angle: window(self.tripoint_angle),
+            max_strain: optional_f32(self.max_strain),
+            min_site_distance: self.min_site_distance,
+            min_crown_distance: self.min_crown_distance,
+        }
+    }
+
+    /// Missing criteria accept every group of points, so files written before
+    /// the criteria existed still load.
+    fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        let bound = |value: &JsonValue, infinite: f32, what: &str| {
+            if value.is_null() {
+                Ok(infinite)
+            } else {
+                as_f32(value).ok_or_else(|| invalid(what))
+            }
+        };
+        let window = |key: &str| -> Result<Window, LatticeError> {
+            Ok(Window::new(
+                bound(&data[key]["min"], f32::NEG_INFINITY, key)?,
+                bound(&data[key]["max"], f32::INFINITY, key)?,
+            ))
+        };
+        Ok(Self {
+            midpoint_distance: window("midpoint_distance")?,
+            tripoint_distance: window("tripoint_distance")?,
+            tripoint_angle: window("tripoint_angle")?,
+            max_strain: bound(&data["max_strain"], f32::INFINITY, "max_strain")?,
+            min_site_distance: bound(&data["min_site_distance"], 0.0, "min_site_distance")?,
+            min_crown_distance: bound(&data["min_crown_distance"], 0.0, "min_crown_distance")?,
+        })
+    }
+}
+
+impl RejectedSite {
+    fn to_json(&self) -> JsonValue {
+        let (reason, value) = match self.reason {
+            Rejection::Distance(distance) => ("distance", distance),
+            Rejection::Angle(angle) => ("angle", angle),
+            Rejection::Strain(strain) => ("strain", strain),
+        };
+        object! {
+            kind: self.kind.name(),
+            points: self.points.iter().map(|p| p.0).collect_vec(),
+            reason: reason,
+            value: value,
+        }
+    }
+
+    fn from_json(data: &JsonValue, point_amount: usize) -> Result<Self, LatticeError> {
+        let kind = SiteKind::from_name(data["kind"].as_str().ok_or_else(|| invalid("rejected"))?);
+        let points = indices_from_json(&data["points"], "rejected")?;
+        if points.iter().any(|&p| p >= point_amount) {
+            return Err(invalid("rejected"));
+        }
+        let value = get_f32(data, "value").map_err(|_| invalid("rejected"))?;
+        let reason = match data["reason"].as_str() {
+            Some("distance") => Rejection::Distance(value),
+            Some("angle") => Rejection::Angle(value),
+            Some("strain") => Rejection::Strain(value),
+            _ => return Err(invalid("rejected")),
+        };
+        Ok(Self {
+            kind,
+            points: points.into_iter().map(LatticeIndex).collect(),
+            reason,
+        })
+    }
+}
+
+/// The points of a saved `Lattice`, of which the connections refer to
+/// `oxygen_amount` oxygens.
+///
+/// # Panics
+/// Will panic when the connections of a new point are poisoned.
+fn points_from_json(
+    data: &JsonValue,
+    oxygen_amount: usize,
+) -> Result<Vec<Arc<LatticePoint>>, LatticeError> {
+    // Ghosts refer to their parent, which is created first.
+    let mut points: Vec<Option<Arc<LatticePoint>>> = vec![None; data.len()];
+    let ghosts_last = data
+        .members()
+        .enumerate()
+        .sorted_by_key(|(_, point)| !point["ghost_of"].is_null());
+    for (number, point) in ghosts_last {
+        let parent = match point["ghost_of"].as_usize() {
+            Some(parent) => Some(
+                points
+                    .get(parent)
+                    .cloned()
+                    .flatten()
+                    .filter(|p| p.ghost_to.is_none())
+                    .ok_or_else(|| invalid("ghost_of"))?,
+            ),
+            None if point["ghost_of"].is_null() => None,
+            None => return Err(invalid("ghost_of")),
+        };
+        // Files saved before bond directions were stored use the old
+        // default.
+        let bond = if point["bond"].is_null() {
+            DOWN
+        } else {
+            match floats_from_json(&point["bond"], "bond")?[..] {
+                [x, y, z] => [x, y, z],
+                _ => return Err(invalid("bond")),
+            }
+        };
+        let new_point = LatticePoint::new(
+            get_f32(point, "x")?,
+            get_f32(point, "y")?,
+            get_f32(point, "z")?,
+            bond,
+            parent,
+        );
+        let connections = indices_from_json(&point["connections"], "connections")?;
+        if connections.iter().any(|&c| c >= oxygen_amount) {
+            return Err(invalid("connections"));
+        }
+        new_point
+            .connected_to
+            .write()
+            .unwrap()
+            .extend(connections.into_iter().map(OxygenIndex));
+        points[number] = Some(new_point);
+    }
+    Ok(points.into_iter().flatten().collect())
+}
+
+impl Lattice {
+    /// Serialize the complete `Lattice`, including the exclusions of the
+    /// oxygens and the file it was created from.
+    ///
+    /// Ghost points are stored with the index of the point they are a ghost of.
+    ///
+    /// # Panics
+    /// Will panic when the connections of a point are poisoned.
+    pub fn to_json(&self) -> JsonValue {
+        let points = zip(&self.points, self.ghost_parents())
+            .map(|(point, ghost_of)| {
+                let ghost_of = ghost_of.map(|parent| parent.0);
+                #[allow(clippy::significant_drop_tightening)]
+                let connections = point
+                    .connected_to
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .map(|c| c.0)
+                    .collect_vec();
+                object! {
+                    x: point.x,
+                    y: point.y,
+                    z: point.z,
+                    bond: point.bond.to_vec(),
+                    ghost_of: ghost_of,
+                    connections: connections,
+                }
+            })
+            .collect_vec();
+
+        let oxygens = self
+            .oxygens
+            .iter()
+            .map(|oxygen| {
+                object! {
+                    x: oxygen.x,
+                    y: oxygen.y,
+                    z: oxygen.z,
+                    kind: oxygen.sitetype.kind.name(),
+                    points: oxygen.sitetype.iter().map(|p| p.0).collect_vec(),
+                    exclusions: oxygen.exclusions.iter().map(|e| e.0).collect_vec(),
+                }
+            })
+            .collect_vec();
+
+        let mut data = header("lattice");
+        data["points"] = points.into();
+        data["oxygens"] = oxygens.into();
+        data["basis"] = self
+            .basis
+            .map_or(JsonValue::Null, |basis| basis.concat().into());
+        data["pbc"] = self.pbc.to_vec().into();
+        data["attachment"] = self.attachment.to_json();
+        data["geometry"] = self.geometry.to_json();
+        data["criteria"] = self.criteria.to_json();
+        data["rejected"] = self
+            .rejected
+            .iter()
+            .map(RejectedSite::to_json)
+            .collect_vec()
+            .into();
+        data["kinds"] = self.kinds.iter().map(SiteKind::name).collect_vec().into();
+        data["node_search_distance"] = self.node_search_distance.into();
+        data["source_file"] = self.source_file.clone().unwrap_or(JsonValue::Null);
+        data
+    }
+
+    /// Deserialize a `Lattice` written by `Lattice::to_json`.
+    ///
+    /// Files of version 1 did not store the search distance of the points,
+    /// which is detected again as `Lattice::python_new` does by default:
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, Lattice};
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let mut data = lattice.to_json();
+    /// data["version"] = 1.into();
+    /// data.remove("kinds");
+    /// data.remove("node_search_distance");
+    /// let loaded = Lattice::from_json(&data)?;
+    /// assert_eq!(
+    ///     loaded.to_json()["node_search_distance"],
+    ///     lattice.to_json()["node_search_distance"]
+    /// );
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when `data` is not a saved `Lattice`, was written by an
+    /// unsupported version or is inconsistent.
+    ///
+    /// # Panics
+    /// Will panic when the connections of a new point are poisoned.
+    pub fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        let version = check_header(data, "lattice")?;
+        let mut lattice = Self::new();
+
+        let point_amount = data["points"].len();
+        let oxygen_amount = data["oxygens"].len();
+        lattice.points = points_from_json(&data["points"], oxygen_amount)?;
+
+        for oxygen in data["oxygens"].members() {
+            let indices = indices_from_json(&oxygen["points"], "points")?;
+            if indices.iter().any(|&i| i >= point_amount) {
+                return Err(invalid("points"));
+            }
+            let indices = indices.into_iter().map(LatticeIndex).collect_vec();
+            let kind = SiteKind::from_name(oxygen["kind"].as_str().ok_or_else(|| invalid("kind"))?);
+            if kind
+                .points()
+                .map_or(indices.is_empty(), |n| n != indices.len())
+            {
+                return Err(invalid("kind"));
+            }
+            let sitetype = SiteType::new(kind, indices);
+            let mut new_oxygen = Oxygen::new(
+                get_f32(oxygen, "x")?,
+                get_f32(oxygen, "y")?,
+                get_f32(oxygen, "z")?,
+                sitetype,
+            );
+            new_oxygen.exclusions = indices_from_json(&oxygen["exclusions"], "exclusions")?
+                .into_iter()
+                .map(OxygenIndex)
+                .collect();
+            if new_oxygen.exclusions.iter().any(|e| e.0 >= oxygen_amount) {
+                return Err(invalid("exclusions"));
+            }
+            lattice.oxygens.push(new_oxygen);
+        }
+
+        lattice.basis = if data["basis"].is_null() {
+            None
+        } else {
+            match floats_from_json(&data["basis"], "basis")?[..] {
+                [ax, ay, az, bx, by, bz, cx, cy, cz] => {
+                    Some([[ax, ay, az], [bx, by, bz], [cx, cy, cz]])
+                }
+                _ => return Err(invalid("basis")),
+            }
+        };
+        lattice.pbc = match data["pbc"].members().map(JsonValue::as_bool).collect_vec()[..] {
+            [Some(x), Some(y), Some(z)] => [x, y, z],
+            _ => return Err(invalid("pbc")),
+        };
+        lattice.periodicity = lattice.find_periodicity();
+        lattice.attachment = AttachmentSpec::from_json(&data["attachment"])?;
+        lattice.geometry = SiteGeometry::from_json(&data["geometry"])?;
+        lattice.criteria = SiteCriteria::from_json(&data["criteria"])?;
+        // Version 1 only knew the built-in kinds, and did not store the search
+        // distance.
+        if version > 1 {
+            lattice.kinds = kinds_from_json(&data["kinds"])?;
+            lattice.node_search_distance = get_f32(data, "node_search_distance")?;
+        } else {
+            lattice.node_search_distance = lattice.detected_node_search_distance();
+        }
+        // Versions before 3 did not store the rejected sites.
+        if version > 2 {
+            lattice.rejected = data["rejected"]
+                .members()
+                .map(|rejected| RejectedSite::from_json(rejected, point_amount))
+                .collect::<Result<_, _>>()?;
+        }
+        lattice.source_file = Some(data["source_file"].clone()).filter(|s| !s.is_null());
+        Ok(lattice)
+    }
+
+    /// The search distance `Lattice::python_new` detects with its default
+    /// margin of 1.1: the squared distance from the first point to its nearest
+    /// neighbour, times the margin.
+    fn detected_node_search_distance(&self) -> f32 {
+        let positions = self.points.iter().map(|p| [p.x, p.y, p.z]).collect_vec();
+        let kdtree: KdTree<_, u64, 3, BINSIZE, u32> = KdTree::from(&positions);
+        positions
+            .first()
+            .and_then(|first| {
+                kdtree
+                    .nearest_n::<SquaredEuclidean>(first, 2)
+                    .get(1)
+                    .map(|nearest| nearest.distance * 1.1)
+            })
+            .unwrap_or(0.0)
+    }
+
+    /// Save the complete `Lattice` to a json file, which can be read with
+    /// `Lattice::load`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{criteria::{SiteCriteria, Window}, test_points::small_points, Lattice};
+    /// let criteria = SiteCriteria {
+    ///     midpoint_distance: Window::new(0.0, 2.0),
+    ///     ..SiteCriteria::default()
+    /// };
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?.with_criteria(criteria);
+    /// lattice.save("small_lattice.json")?;
+    /// let loaded = Lattice::load("small_lattice.json")?;
+    /// # std::fs::remove_file("small_lattice.json")?;
+    /// assert_eq!(loaded.to_json().dump(), lattice.to_json().dump());
+    /// assert!(!loaded.rejected_sites().is_empty());
+    /// assert_eq!(loaded.rejected_sites(), lattice.rejected_sites());
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when the file can not be written.
+    pub fn save(&self, filename: &str) -> Result<(), LatticeError> {
+        write_file(filename, &self.to_json())
+    }
+
+    /// Load a `Lattice` saved with `Lattice::save`.
+    ///
+    /// # Errors
+    /// Returns an error when the file can not be read or does not contain a
+    /// valid saved `Lattice`.
+    pub fn load(filename: &str) -> Result<Self, LatticeError> {
+        Self::from_json(&read_file(filename)?)
+    }
+}
+
+impl BitArraySettings {
+    /// Serialize the settings, which can be read back with
+    /// `BitArraySettings::from_json`.
+    pub fn to_json(&self) -> JsonValue {
+        let solve_filter = match self.solve_filter {
+            BitArrayFilter::None => "None",
+            BitArrayFilter::Similarity => "Similarity",
+            BitArrayFilter::SimTrees => "SimTrees",
+            BitArrayFilter::Flipped => "Flipped",
+            BitArrayFilter::InsideOut => "InsideOut",
+        };
+        let mut data = header("settings");
+        data["max_singlets"] = self.max_singlets.into();
+        data["difference_distance"] = self.difference_distance.into();
+        data["max_x"] = self.max_x.into();
+        data["max_y"] = self.max_y.into();
+        data["solve_filter"] = solve_filter.into();
+        data["strategy"] = match self.strategy {
+            SolveStrategy::BreadthFirst => "BreadthFirst",
+            SolveStrategy::DepthFirst => "DepthFirst",
+        }
+        .into();
+        data
+    }
+
+    /// Deserialize settings written by `BitArraySettings::to_json`.
+    ///
+    /// # Errors
+    /// Returns an error when `data` is not saved `BitArraySettings` or was
+    /// written by an unsupported version.
+    pub fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        check_header(data, "settings")?;
+        let solve_filter = match data["solve_filter"].as_str() {
+            Some("None") => BitArrayFilter::None,
+            Some("Similarity") => BitArrayFilter::Similarity,
+            Some("SimTrees") => BitArrayFilter::SimTrees,
+            Some("Flipped") => BitArrayFilter::Flipped,
+            Some("InsideOut") => BitArrayFilter::InsideOut,
+            _ => return Err(invalid("solve_filter")),
+        };
+        // Settings saved before the strategy existed solved breadth-first.
+        let strategy = match data["strategy"].as_str() {
+            None if data["strategy"].is_null() => SolveStrategy::BreadthFirst,
+            Some("BreadthFirst") => SolveStrategy::BreadthFirst,
+            Some("DepthFirst") => SolveStrategy::DepthFirst,
+            _ => return Err(invalid("strategy")),
+        };
+        Ok(Self::create(
+            get_usize(data, "max_singlets")?,
+            get_f32(data, "difference_distance")?,
+            (get_f32(data, "max_x")?, get_f32(data, "max_y")?),
+            solve_filter,
+        )
+        .with_strategy(strategy))
+    }
+}
+
+impl BitArrayRepresentation {
+    /// Serialize the complete `BitArrayRepresentation`, including its
+    /// settings, which can be read back with
+    /// `BitArrayRepresentation::from_json`.
+    pub fn to_json(&self) -> JsonValue {
+        let mut data = header("representation");
+        data["filled_sites"] = bitset_to_json(self.filled_sites());
+        data["exclusion_matrix"] = self
+            .exclusion_matrix()
+            .iter()
+            .map(bitset_to_json)
+            .collect_vec()
+            .into();
+        data["distances"] = self.distances.to_json();
+        data["kinds"] = self
+            .kind_masks()
+            .iter()
+            .map(|(kind, _)| kind.name())
+            .collect_vec()
+            .into();
+        data["kind_masks"] = self
+            .kind_masks()
+            .iter()
+            .map(|(_, mask)| bitset_to_json(mask))
+            .collect_vec()
+            .into();
+        data["filter"] = self.filter.as_ref().map_or(JsonValue::Null, bitset_to_json);
+        data["options"] = self.options.to_json();
+        data
+    }
+
+    /// Deserialize a `BitArrayRepresentation` written by
+    /// `BitArrayRepresentation::to_json`.
+    ///
+    /// # Errors
+    /// Returns an error when `data` is not a saved `BitArrayRepresentation`,
+    /// was written by an unsupported version or is inconsistent.
+    pub fn from_json(data: &JsonValue) -> Result<Self, LatticeError> {
+        let version = check_header(data, "representation")?;
+        let filled_sites = bitset_from_json(&data["filled_sites"], "filled_sites")?;
+        let size = filled_sites.len();
+
+        let exclusion_matrix = data["exclusion_matrix"]
+            .members()
+            .map(|row| bitset_from_json(row, "exclusion_matrix"))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Versions before 4 stored the distances between all sites.
+        let distances = if version > 3 {
+            SiteDistances::from_json(&data["distances"]).ok_or_else(|| invalid("distances"))?
+        } else {
+            let distances_matrix = data["distances_matrix"]
+                .members()
+                .map(|row| floats_from_json(row, "distances_matrix"))
+                .collect::<Result<Vec<_>, _>>()?;
+            if distances_matrix.iter().any(|row| row.len() != size) {
+                return Err(invalid("distances_matrix"));
+            }
+            SiteDistances::from_matrix(distances_matrix)
+        };
+        let kind_masks = if version > 1 {
+            let kinds = kinds_from_json(&data["kinds"])?;
+            if data["kind_masks"].len() != kinds.len() {
+                return Err(invalid("kind_masks"));
+            }
+            zip(kinds, data["kind_masks"].members())
+                .map(|(kind, mask)| Ok((kind, bitset_from_json(mask, "kind_masks")?)))
+                .collect::<Result<Vec<_>, LatticeError>>()?
+        } else {
+            zip(
+                SiteKind::BUILT_IN,
+                ["tripoint_mask", "midpoint_mask", "singlet_mask"],
+            )
+            .map(|(kind, key)| Ok((kind, bitset_from_json(&data[key], key)?)))
+            .collect::<Result<Vec<_>, LatticeError>>()?
+        };
+        let filter = if data["filter"].is_null() {
+            None
+        } else {
+            Some(bitset_from_json(&data["filter"], "filter")?)
+        };
+
+        if exclusion_matrix.len() != size || exclusion_matrix.iter().any(|row| row.len() != size) {
+            return Err(invalid("exclusion_matrix"));
+        }
+        if distances.len() != size {
+            return Err(invalid("distances"));
+        }
+        if kind_masks.iter().any(|(_, mask)| mask.len() != size) {
+            return Err(invalid("masks"));
+        }
+        if filter
+            .as_ref()
+            .is_some_and(|filter| filter.count_ones(..) != size)
+        {
+            return Err(invalid("filter"));
+        }
+
+        Ok(Self::from_parts(
+            filled_sites,
+            exclusion_matrix,
+            distances,
+            kind_masks,
+            filter,
+            BitArraySettings::from_json(&data["options"])?,
+        ))
+    }
+
+    /// Save the `BitArrayRepresentation` and its settings to a json file, so
+    /// it can be solved later with `BitArrayRepresentation::load`.
+    ///
+    /// ```
+    /// # use crystacean_rs::{test_points::small_points, BitArrayRepresentation, BitArraySettings, Lattice};
+    /// let lattice = Lattice::python_new(small_points(), 1.1, true)?;
+    /// let intermediary = lattice.get_intermediary(BitArraySettings::default(&lattice));
+    /// intermediary.save("small_intermediary.json")?;
+    /// let loaded = BitArrayRepresentation::load("small_intermediary.json")?;
+    /// # std::fs::remove_file("small_intermediary.json")?;
+    /// assert_eq!(loaded.solve(true, true), intermediary.solve(true, true));
+    /// # Ok::<(), crystacean_rs::LatticeError>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error when the file can not be written.
+    pub fn save(&self, filename: &str) -> Result<(), LatticeError> {
+        write_file(filename, &self.to_json())
+    }
+
+    /// Load a `BitArrayRepresentation` saved with `BitArrayRepresentation::save`.
+    ///
+    /// # Errors
+    /// Returns an error when the file can not be read or does not contain a
+    /// valid saved `BitArrayRepresentation`.
+    pub fn load(filename: &str) -> Result<Self, LatticeError> {
+        Self::from_json(&read_file(filename)?)
+    }
+}
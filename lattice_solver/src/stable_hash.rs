@@ -0,0 +1,35 @@
+//! A hash which is the same on every machine and in every run, unlike the
+//! hashers of the standard library. Used for identifiers which end up in file
+//! names and result tables.
+
+/// The 64-bit FNV-1a hash.
+pub struct StableHasher(u64);
+
+impl StableHasher {
+    pub const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Continue from an earlier hash.
+    pub const fn from_hash(hash: u64) -> Self {
+        Self(hash)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Write a coordinate rounded to a thousandth of an ångström, so rounding
+    /// errors in the last digits do not change the hash.
+    pub fn write_coordinate(&mut self, value: f32) {
+        let rounded = (f64::from(value) * 1000.0).round() as i64;
+        self.write(&rounded.to_le_bytes());
+    }
+
+    pub const fn finish(&self) -> u64 {
+        self.0
+    }
+}
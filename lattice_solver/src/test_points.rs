@@ -1,129 +1,21 @@
-#![allow(clippy::excessive_precision)]
+//! Small lattices for tests, examples and benchmarks, generated with
+//! `basis_vectors::lattice_from_basis_vectors`.
+use crate::{
+    basis_vectors::{lattice_from_basis_vectors, SIC_A, SIC_B},
+    InputPoint,
+};
 
-pub fn lattice_points() -> Vec<(Vec<f32>, Vec<Vec<f32>>)> {
-    vec![
-        (vec![1.5, 0.0], vec![vec![1.5, 5.196152422706632]]),
-        (
-            vec![0.0, 0.0],
-            vec![
-                vec![0.0, 5.196152422706632],
-                vec![6.0, 0.0],
-                vec![6.0, 5.196152422706632],
-            ],
-        ),
-        (vec![2.25, 1.299038105676658], vec![]),
-        (
-            vec![0.75, 1.299038105676658],
-            vec![vec![6.75, 1.299038105676658]],
-        ),
-        (vec![1.5, 2.598076211353316], vec![]),
-        (
-            vec![0.0, 2.598076211353316],
-            vec![vec![6.0, 2.598076211353316]],
-        ),
-        (vec![2.25, 3.897114317029974], vec![]),
-        (
-            vec![0.75, 3.897114317029974],
-            vec![vec![6.75, 3.897114317029974]],
-        ),
-        (vec![4.5, 0.0], vec![vec![4.5, 5.196152422706632]]),
-        (vec![3.0, 0.0], vec![vec![3.0, 5.196152422706632]]),
-        (vec![5.25, 1.299038105676658], vec![]),
-        (vec![3.75, 1.299038105676658], vec![]),
-        (vec![4.5, 2.598076211353316], vec![]),
-        (vec![3.0, 2.598076211353316], vec![]),
-        (vec![5.25, 3.897114317029974], vec![]),
-        (vec![3.75, 3.897114317029974], vec![]),
-    ]
+/// A 2 by 2 supercell with a nearest neighbour distance of 1.5.
+pub fn lattice_points() -> Vec<InputPoint> {
+    lattice_from_basis_vectors(2, 2, [1.5, 0.0, 0.0], [0.75, 1.299038105676658, 0.0])
 }
 
-pub fn small_points() -> Vec<(Vec<f32>, Vec<Vec<f32>>)> {
-    vec![
-        (vec![3.076, 0.0], vec![vec![3.076, 5.327788284081867]]),
-        (
-            vec![0.0, 0.0],
-            vec![
-                vec![0.0, 5.327788284081867],
-                vec![6.152, 0.0],
-                vec![6.152, 5.327788284081867],
-            ],
-        ),
-        (vec![4.614, 2.6638941420409337], vec![]),
-        (
-            vec![1.538, 2.6638941420409337],
-            vec![vec![7.69, 2.6638941420409337]],
-        ),
-    ]
+/// A single silicon carbide unit cell.
+pub fn small_points() -> Vec<InputPoint> {
+    lattice_from_basis_vectors(1, 1, SIC_A, SIC_B)
 }
 
-pub fn huge_points() -> Vec<(Vec<f32>, Vec<Vec<f32>>)> {
-    vec![
-        (
-            vec![3.076, 0.0],
-            vec![vec![3.0759999999999996, 15.983364852245602]],
-        ),
-        (
-            vec![0.0, 0.0],
-            vec![
-                vec![0.0, 15.983364852245602],
-                vec![18.456, 0.0],
-                vec![18.456, 15.983364852245602],
-            ],
-        ),
-        (vec![4.614, 2.6638941420409337], vec![]),
-        (
-            vec![1.538, 2.6638941420409337],
-            vec![vec![19.994, 2.6638941420409337]],
-        ),
-        (vec![3.076, 5.327788284081867], vec![]),
-        (
-            vec![0.0, 5.327788284081867],
-            vec![vec![18.456, 5.327788284081867]],
-        ),
-        (vec![4.614, 7.991682426122801], vec![]),
-        (
-            vec![1.5379999999999998, 7.991682426122801],
-            vec![vec![19.994, 7.991682426122801]],
-        ),
-        (vec![3.076, 10.655576568163735], vec![]),
-        (
-            vec![0.0, 10.655576568163735],
-            vec![vec![18.456, 10.655576568163735]],
-        ),
-        (vec![4.614000000000001, 13.31947071020467], vec![]),
-        (
-            vec![1.5380000000000003, 13.31947071020467],
-            vec![vec![19.994, 13.31947071020467]],
-        ),
-        (vec![9.228, 0.0], vec![vec![9.228, 15.983364852245602]]),
-        (
-            vec![6.152, 0.0],
-            vec![vec![6.151999999999999, 15.983364852245602]],
-        ),
-        (vec![10.766, 2.6638941420409337], vec![]),
-        (vec![7.69, 2.6638941420409337], vec![]),
-        (vec![9.228, 5.327788284081867], vec![]),
-        (vec![6.152, 5.327788284081867], vec![]),
-        (vec![10.766, 7.991682426122801], vec![]),
-        (vec![7.6899999999999995, 7.991682426122801], vec![]),
-        (vec![9.228, 10.655576568163735], vec![]),
-        (vec![6.152, 10.655576568163735], vec![]),
-        (vec![10.766, 13.31947071020467], vec![]),
-        (vec![7.69, 13.31947071020467], vec![]),
-        (
-            vec![15.38, 0.0],
-            vec![vec![15.379999999999999, 15.983364852245602]],
-        ),
-        (vec![12.304, 0.0], vec![vec![12.304, 15.983364852245602]]),
-        (vec![16.918, 2.6638941420409337], vec![]),
-        (vec![13.842, 2.6638941420409337], vec![]),
-        (vec![15.38, 5.327788284081867], vec![]),
-        (vec![12.304, 5.327788284081867], vec![]),
-        (vec![16.918, 7.991682426122801], vec![]),
-        (vec![13.841999999999999, 7.991682426122801], vec![]),
-        (vec![15.379999999999999, 10.655576568163735], vec![]),
-        (vec![12.304, 10.655576568163735], vec![]),
-        (vec![16.918, 13.31947071020467], vec![]),
-        (vec![13.842, 13.31947071020467], vec![]),
-    ]
+/// A 3 by 3 silicon carbide supercell.
+pub fn huge_points() -> Vec<InputPoint> {
+    lattice_from_basis_vectors(3, 3, SIC_A, SIC_B)
 }
@@ -0,0 +1,35 @@
+//! Small helpers for working with three dimensional vectors.
+
+pub fn add(one: [f32; 3], two: [f32; 3]) -> [f32; 3] {
+    [one[0] + two[0], one[1] + two[1], one[2] + two[2]]
+}
+
+pub fn sub(one: [f32; 3], two: [f32; 3]) -> [f32; 3] {
+    [one[0] - two[0], one[1] - two[1], one[2] - two[2]]
+}
+
+pub fn scale(vector: [f32; 3], factor: f32) -> [f32; 3] {
+    vector.map(|v| v * factor)
+}
+
+pub fn cross(one: [f32; 3], two: [f32; 3]) -> [f32; 3] {
+    [
+        one[1].mul_add(two[2], -(one[2] * two[1])),
+        one[2].mul_add(two[0], -(one[0] * two[2])),
+        one[0].mul_add(two[1], -(one[1] * two[0])),
+    ]
+}
+
+pub fn dot(one: [f32; 3], two: [f32; 3]) -> f32 {
+    one[0].mul_add(two[0], one[1].mul_add(two[1], one[2] * two[2]))
+}
+
+pub fn norm(vector: [f32; 3]) -> f32 {
+    dot(vector, vector).sqrt()
+}
+
+/// Returns `vector` scaled to unit length, or `None` when it has no length.
+pub fn normalize(vector: [f32; 3]) -> Option<[f32; 3]> {
+    let length = norm(vector);
+    (length > f32::EPSILON).then(|| scale(vector, 1.0 / length))
+}
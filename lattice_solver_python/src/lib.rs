@@ -8,16 +8,82 @@
 //! ?(?:(,\s*[a-zA-Z_]*): [&a-zA-Z0-9]*(?:<[&a-zA-Z_<>(), 0-9]*>|\([&a-zA-Z_<>(), 0-9]*\))?)?
 //! ?(?:(,\s*[a-zA-Z_]*): [&a-zA-Z0-9]*(?:<[&a-zA-Z_<>(), 0-9]*>|\([&a-zA-Z_<>(), 0-9]*\))?)?
 //! ?[\s,]*\)"g
+// The pyo3 0.22 macros trigger this lint for every method returning `PyResult`.
+#![allow(clippy::useless_conversion)]
+use ::crystacean_rs::basis_vectors::{SIC_A, SIC_B};
+use ::crystacean_rs::criteria::{SiteCriteria, Window};
+use ::crystacean_rs::geometry::SiteGeometry;
+use ::crystacean_rs::AttachmentSpec;
 use ::crystacean_rs::BitArrayFilter;
 use ::crystacean_rs::BitArraySettings;
+use ::crystacean_rs::CancelToken;
+use ::crystacean_rs::Checkpoints;
+use ::crystacean_rs::LatticeError;
+use ::crystacean_rs::MarkerRegion;
+use ::crystacean_rs::SiteKind;
+use ::crystacean_rs::SolutionCounts;
+use ::crystacean_rs::SolveLimits;
+use ::crystacean_rs::SolveStrategy;
+use pyo3::exceptions::{PyKeyError, PyOSError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
 use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use ::crystacean_rs::site_filter::SiteFilter as WrappedFilter;
 use ::crystacean_rs::BitArrayRepresentation as WrappedRepresentation;
 use ::crystacean_rs::BitArraySolution as WrappedSolution;
+use ::crystacean_rs::CandidateSite as WrappedSite;
+use ::crystacean_rs::DepthFirstSearch;
 use ::crystacean_rs::Lattice as WrappedLattice;
 
+/// Convert a `LatticeError` into the matching Python exception.
+fn lattice_error(err: LatticeError) -> PyErr {
+    let message = err.to_string();
+    match err {
+        LatticeError::Io(_) => PyOSError::new_err(message),
+        LatticeError::MissingKey(_) => PyKeyError::new_err(message),
+        LatticeError::NotFromFile => PyRuntimeError::new_err(message),
+        LatticeError::Json(_)
+        | LatticeError::MalformedArray(_)
+        | LatticeError::CellShape(_)
+        | LatticeError::PointDimension(_)
+        | LatticeError::NoAttachmentSites
+        | LatticeError::InvalidSave(_)
+        | LatticeError::CheckpointMismatch(_)
+        | LatticeError::InvalidGeometry(_)
+        | LatticeError::UnsupportedVersion(_) => PyValueError::new_err(message),
+    }
+}
+
+fn seconds(value: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(value)
+        .map_err(|_| PyValueError::new_err(format!("{value} is not a valid amount of seconds")))
+}
+
+/// Checkpoints of a breadth-first solve of `representation`.
+fn checkpoints(
+    representation: &WrappedRepresentation,
+    path: PathBuf,
+    interval: f64,
+    resume: bool,
+) -> PyResult<Checkpoints> {
+    if representation.options.strategy == SolveStrategy::DepthFirst {
+        return Err(PyValueError::new_err(
+            "depth-first solves can not be checkpointed",
+        ));
+    }
+    let checkpoints = Checkpoints::new(path, seconds(interval)?);
+    Ok(if resume {
+        checkpoints.resuming()
+    } else {
+        checkpoints
+    })
+}
+
 #[pyclass]
 struct BitArraySolution {
     wrapped: WrappedSolution,
@@ -42,40 +108,223 @@ struct SiteFilter {
 /// A symbolic representation of the lattice, usefull for finding surface structures
 /// efficiently.
 struct BitArrayRepresentation {
-    wrapped: WrappedRepresentation,
+    wrapped: Arc<WrappedRepresentation>,
+}
+
+#[pyclass]
+/// A generator of solutions, created by `BitArrayRepresentation.solve_iter()`.
+/// The search continues in the background of every `next` call, without
+/// holding the GIL.
+struct SolutionIterator {
+    representation: Arc<WrappedRepresentation>,
+    search: DepthFirstSearch,
+}
+
+#[pymethods]
+impl SolutionIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(mut slf: PyRefMut<'_, Self>) -> Option<BitArraySolution> {
+        let py = slf.py();
+        let iterator = &mut *slf;
+        py.allow_threads(|| iterator.search.next_solution(&iterator.representation))
+            .map(|a| BitArraySolution { wrapped: a })
+    }
 }
 
 #[pymethods]
 impl BitArrayRepresentation {
     /// Start finding possible surface structures.
     ///  - find_all: when false, stops after a single solution has been found.
-    #[pyo3(signature = (find_all, silent=false))]
-    fn solve(&self, find_all: bool, silent: bool) -> Vec<BitArraySolution> {
-        self.wrapped
-            .solve(find_all, silent)
+    ///  - checkpoint: file to which the progress is written, at most once every
+    ///    `checkpoint_interval` seconds. The file is removed when solving is
+    ///    done. Raises a ValueError for a representation created with
+    ///    `depth_first`, as only breadth-first solves can be checkpointed.
+    ///  - resume: continue from the checkpoint file when it exists, instead of
+    ///    starting over.
+    #[pyo3(signature = (find_all, silent=false, checkpoint=None, checkpoint_interval=600.0, resume=false))]
+    fn solve(
+        &self,
+        py: Python<'_>,
+        find_all: bool,
+        silent: bool,
+        checkpoint: Option<PathBuf>,
+        checkpoint_interval: f64,
+        resume: bool,
+    ) -> PyResult<Vec<BitArraySolution>> {
+        let solutions = match checkpoint {
+            None => self.wrapped.solve(find_all, silent),
+            Some(path) => {
+                let checkpoints = checkpoints(&self.wrapped, path, checkpoint_interval, resume)?;
+                py.allow_threads(|| {
+                    self.wrapped
+                        .solve_checkpointed(find_all, silent, &checkpoints)
+                })
+                .map_err(lattice_error)?
+            }
+        };
+        Ok(solutions
             .into_iter()
             .map(|a| BitArraySolution { wrapped: a })
-            .collect()
+            .collect())
     }
 
-    /// Start solving using the multithreaded algorithm.
-    #[pyo3(signature = (find_all, silent=false))]
-    fn solve_parallel(&self, find_all: bool, silent: bool) -> Vec<BitArraySolution> {
-        self.wrapped
-            .solve_parallel(find_all, silent)
+    /// Find possible surface structures like `solve`, but stop early when a
+    /// limit is reached. Ctrl-C stops solving too. Returns the structures found
+    /// until then, and why solving stopped: "complete", "max_solutions",
+    /// "time_budget", "max_frontier" or "cancelled".
+    ///  - parallel: solve using the multithreaded algorithm.
+    ///  - time_budget: the most time to spend solving, in seconds.
+    ///  - max_frontier: the most structures waiting to be explored.
+    ///  - report: also return a `SolveReport` with statistics of the solve.
+    #[pyo3(signature = (find_all, silent=false, parallel=false, max_solutions=None, time_budget=None, max_frontier=None, report=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn solve_limited(
+        &self,
+        py: Python<'_>,
+        find_all: bool,
+        silent: bool,
+        parallel: bool,
+        max_solutions: Option<usize>,
+        time_budget: Option<f64>,
+        max_frontier: Option<usize>,
+        report: bool,
+    ) -> PyResult<PyObject> {
+        let token = CancelToken::new();
+        let limits = SolveLimits {
+            max_solutions,
+            time_budget: time_budget.map(seconds).transpose()?,
+            max_frontier,
+            cancel: Some(token.clone()),
+        };
+        let wrapped = &self.wrapped;
+        let outcome = thread::scope(|scope| {
+            let solver = scope.spawn(|| {
+                if parallel {
+                    wrapped.solve_parallel_limited(find_all, silent, &limits)
+                } else {
+                    wrapped.solve_limited(find_all, silent, &limits)
+                }
+            });
+            // Python only handles Ctrl-C while it holds the GIL.
+            while !solver.is_finished() {
+                py.allow_threads(|| thread::sleep(Duration::from_millis(100)));
+                if py.check_signals().is_err() {
+                    token.cancel();
+                }
+            }
+            solver.join()
+        })
+        .map_err(|_| PyRuntimeError::new_err("solving panicked"))?;
+        let solutions: Vec<BitArraySolution> = outcome
+            .solutions
             .into_iter()
             .map(|a| BitArraySolution { wrapped: a })
-            .collect()
+            .collect();
+        let status = outcome.status.name();
+        Ok(if report {
+            let report = SolveReport::new(py, &outcome.report)?;
+            (solutions, status, report).into_py(py)
+        } else {
+            (solutions, status).into_py(py)
+        })
+    }
+
+    /// Start finding possible surface structures depth-first, which uses far
+    /// less memory on large lattices. Finds the same structures as `solve`.
+    ///  - find_all: when false, only returns the structures with the fewest sites.
+    ///  - parallel: search on all threads, where idle threads take over
+    ///    unexplored parts of the search from busy ones.
+    #[pyo3(signature = (find_all, silent=false, parallel=false))]
+    fn solve_depth_first(
+        &self,
+        py: Python<'_>,
+        find_all: bool,
+        silent: bool,
+        parallel: bool,
+    ) -> Vec<BitArraySolution> {
+        py.allow_threads(|| {
+            if parallel {
+                self.wrapped.solve_depth_first_parallel(find_all, silent)
+            } else {
+                self.wrapped.solve_depth_first(find_all, silent)
+            }
+        })
+        .into_iter()
+        .map(|a| BitArraySolution { wrapped: a })
+        .collect()
+    }
+
+    /// Iterate over all possible surface structures, which are found
+    /// depth-first while iterating. Structures can be exported while the search
+    /// continues, and breaking out of the loop stops the search.
+    fn solve_iter(&self) -> SolutionIterator {
+        SolutionIterator {
+            representation: self.wrapped.clone(),
+            search: DepthFirstSearch::new(&self.wrapped),
+        }
+    }
+
+    /// Count the possible surface structures without keeping them. Returns a
+    /// dict from the amount of tripoints, midpoints and singlets of a
+    /// structure to the amount of structures with that composition.
+    ///  - parallel: count on all threads.
+    #[pyo3(signature = (parallel=false))]
+    fn count_solutions<'py>(
+        &self,
+        py: Python<'py>,
+        parallel: bool,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let counts = py.allow_threads(|| self.wrapped.count_solutions(parallel));
+        compositions(py, &counts)
+    }
+
+    /// Start solving using the multithreaded algorithm. Finds the same
+    /// structures as `solve`, also when a solve filter is used.
+    ///  - checkpoint, resume: as with `solve`, but only written between depths.
+    #[pyo3(signature = (find_all, silent=false, checkpoint=None, checkpoint_interval=600.0, resume=false))]
+    fn solve_parallel(
+        &self,
+        py: Python<'_>,
+        find_all: bool,
+        silent: bool,
+        checkpoint: Option<PathBuf>,
+        checkpoint_interval: f64,
+        resume: bool,
+    ) -> PyResult<Vec<BitArraySolution>> {
+        let solutions = match checkpoint {
+            None => self.wrapped.solve_parallel(find_all, silent),
+            Some(path) => {
+                let checkpoints = checkpoints(&self.wrapped, path, checkpoint_interval, resume)?;
+                py.allow_threads(|| {
+                    self.wrapped
+                        .solve_parallel_checkpointed(find_all, silent, &checkpoints)
+                })
+                .map_err(lattice_error)?
+            }
+        };
+        Ok(solutions
+            .into_iter()
+            .map(|a| BitArraySolution { wrapped: a })
+            .collect())
     }
 
     /// Create a new `BitArrayRepresentation` by removing possible sites with a
     /// `SiteFilter`.
     fn filtered(&self, filter: SiteFilter) -> BitArrayRepresentation {
         BitArrayRepresentation {
-            wrapped: self.wrapped.filtered(filter.wrapped),
+            wrapped: Arc::new(self.wrapped.filtered(filter.wrapped)),
         }
     }
 
+    /// Save the representation and its settings to a json file, which can be
+    /// read with `load_intermediary`.
+    fn save(&self, filename: String) -> PyResult<()> {
+        self.wrapped.save(&filename).map_err(lattice_error)
+    }
+
     fn __str__(&self) -> String {
         self.wrapped.__str__()
     }
@@ -85,6 +334,177 @@ impl BitArrayRepresentation {
     }
 }
 
+#[pyclass(get_all)]
+/// An attachment point of a `Lattice`.
+///  - `bond`: unit vector from the substrate atom to this point.
+///  - `ghost_of`: the index of the point this point is a periodic image of.
+///  - `sites`: the indices of the candidate sites connected to this point.
+struct AttachmentPoint {
+    index: usize,
+    position: [f32; 3],
+    bond: [f32; 3],
+    ghost_of: Option<usize>,
+    sites: Vec<usize>,
+}
+
+#[pymethods]
+impl AttachmentPoint {
+    fn __repr__(&self) -> String {
+        format!(
+            "AttachmentPoint(index={}, position={:?}, ghost_of={:?})",
+            self.index, self.position, self.ghost_of
+        )
+    }
+}
+
+#[pyclass(get_all)]
+/// A candidate site of a `Lattice`.
+///  - `kind`: "singlet", "midpoint", "tripoint" or the name of a custom kind.
+///  - `points`: the indices of the attachment points bridged by this site.
+///  - `exclusions`: the indices of the sites which can not be occupied
+///    together with this site.
+struct CandidateSite {
+    index: usize,
+    kind: String,
+    position: [f32; 3],
+    points: Vec<usize>,
+    exclusions: Vec<usize>,
+}
+
+#[pymethods]
+impl CandidateSite {
+    fn __repr__(&self) -> String {
+        format!(
+            "CandidateSite(index={}, kind={}, position={:?}, points={:?})",
+            self.index, self.kind, self.position, self.points
+        )
+    }
+}
+
+impl From<WrappedSite<'_>> for CandidateSite {
+    fn from(site: WrappedSite<'_>) -> Self {
+        Self {
+            index: site.index.0,
+            kind: site.kind.name().to_string(),
+            position: site.position,
+            points: site.points.iter().map(|p| p.0).collect(),
+            exclusions: site.exclusions.iter().map(|e| e.0).collect(),
+        }
+    }
+}
+
+#[pyclass(get_all)]
+/// A group of attachment points on which no site was placed.
+///  - `kind`: "midpoint" or "tripoint".
+///  - `points`: the indices of the attachment points.
+///  - `reason`: the rule the points broke.
+struct RejectedSite {
+    kind: String,
+    points: Vec<usize>,
+    reason: String,
+}
+
+#[pymethods]
+impl RejectedSite {
+    fn __repr__(&self) -> String {
+        format!(
+            "RejectedSite(kind={}, points={:?}, reason={})",
+            self.kind, self.points, self.reason
+        )
+    }
+}
+
+#[pyclass(get_all)]
+#[derive(Clone)]
+/// What happened at one depth of a solve, see `SolveReport`.
+///  - `explored`: the amount of structures explored at this depth.
+///  - `pruned_by_possibilities`: explored structures which can not lead to a
+///    solution.
+///  - `pruned_by_filter`: structures of the next depth removed by the solve
+///    filter.
+///  - `seconds`: time spent on this depth, zero when solving depth-first.
+struct DepthStatistics {
+    depth: usize,
+    explored: usize,
+    pruned_by_possibilities: usize,
+    pruned_by_filter: usize,
+    solutions: usize,
+    seconds: f64,
+}
+
+#[pyclass]
+/// Statistics of a solve, returned by `BitArrayRepresentation.solve_limited`.
+struct SolveReport {
+    /// The settings of the solved `BitArrayRepresentation`.
+    #[pyo3(get)]
+    settings: Py<PyDict>,
+    #[pyo3(get)]
+    depths: Vec<DepthStatistics>,
+    /// The amount of solutions per amount of tripoints, midpoints and singlets.
+    #[pyo3(get)]
+    compositions: Py<PyDict>,
+    /// The most structures held at once.
+    #[pyo3(get)]
+    peak_candidates: usize,
+    /// An estimate of the memory used by `peak_candidates`, in bytes.
+    #[pyo3(get)]
+    peak_memory: usize,
+    #[pyo3(get)]
+    seconds: f64,
+    text: String,
+}
+
+impl SolveReport {
+    fn new(py: Python<'_>, report: &::crystacean_rs::SolveReport) -> PyResult<Self> {
+        let settings = PyDict::new_bound(py);
+        settings.set_item("max_singlets", report.settings.max_singlets)?;
+        settings.set_item("difference_distance", report.settings.difference_distance)?;
+        settings.set_item("max_x", report.settings.max_x)?;
+        settings.set_item("max_y", report.settings.max_y)?;
+        settings.set_item(
+            "solve_filter",
+            format!("{:?}", report.settings.solve_filter),
+        )?;
+        settings.set_item("strategy", format!("{:?}", report.settings.strategy))?;
+        Ok(Self {
+            settings: settings.unbind(),
+            depths: report
+                .depths
+                .iter()
+                .map(|depth| DepthStatistics {
+                    depth: depth.depth,
+                    explored: depth.explored,
+                    pruned_by_possibilities: depth.pruned_by_possibilities,
+                    pruned_by_filter: depth.pruned_by_filter,
+                    solutions: depth.solutions,
+                    seconds: depth.elapsed.as_secs_f64(),
+                })
+                .collect(),
+            compositions: compositions(py, &report.compositions)?.unbind(),
+            peak_candidates: report.peak_candidates,
+            peak_memory: report.peak_memory,
+            seconds: report.elapsed.as_secs_f64(),
+            text: report.to_string(),
+        })
+    }
+}
+
+#[pymethods]
+impl SolveReport {
+    fn __str__(&self) -> String {
+        self.text.clone()
+    }
+}
+
+/// A dict from the amount of sites of every kind to the amount of solutions.
+fn compositions<'py>(py: Python<'py>, counts: &SolutionCounts) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (composition, count) in &counts.counts {
+        dict.set_item(PyTuple::new_bound(py, composition), count)?;
+    }
+    Ok(dict)
+}
+
 #[pyclass]
 /// A class representing the lattice.
 struct Lattice {
@@ -100,10 +520,35 @@ impl Lattice {
         input_lattice: Vec<(Vec<f32>, Vec<Vec<f32>>)>,
         distance_margin: f32,
         autodetect_margin: bool,
-    ) -> Self {
-        Lattice {
-            wrapped: WrappedLattice::python_new(input_lattice, distance_margin, autodetect_margin),
-        }
+    ) -> PyResult<Self> {
+        Ok(Lattice {
+            wrapped: WrappedLattice::python_new(input_lattice, distance_margin, autodetect_margin)
+                .map_err(lattice_error)?,
+        })
+    }
+
+    /// Returns all attachment points of the lattice, including ghosts.
+    fn attachment_points(&self) -> Vec<AttachmentPoint> {
+        self.wrapped
+            .attachment_points()
+            .map(|point| AttachmentPoint {
+                index: point.index.0,
+                position: point.position,
+                bond: point.bond,
+                ghost_of: point.ghost_of.map(|p| p.0),
+                sites: point.sites.iter().map(|s| s.0).collect(),
+            })
+            .collect()
+    }
+
+    /// Returns all candidate sites of the lattice.
+    fn sites(&self) -> Vec<CandidateSite> {
+        self.wrapped.sites().map(CandidateSite::from).collect()
+    }
+
+    /// Returns the cell vectors of the lattice, if it has them.
+    fn basis(&self) -> Option<[[f32; 3]; 3]> {
+        self.wrapped.basis()
     }
 
     /// Returns the coordinates of the lattice points in two lists. Use with the * star operator in a plt.plot function:
@@ -146,48 +591,71 @@ impl Lattice {
         self.wrapped.singlets_to_plot()
     }
 
-    #[pyo3(signature = (max_singlets=2, difference_distance=0.05, max=None, use_filter=false))]
+    /// Returns the coordinates of the sites of the given kind in two lists. Use with the * star operator in a plt.plot function:
+    /// ```python
+    /// plt.plot(*solved_lattice.sites_to_plot("midpoint"), "o")
+    /// ```
+    fn sites_to_plot(&self, kind: &str) -> (Vec<f32>, Vec<f32>) {
+        self.wrapped.sites_to_plot(&SiteKind::from_name(kind))
+    }
+
+    #[pyo3(signature = (
+        max_singlets=2,
+        difference_distance=0.05,
+        max=None,
+        use_filter=false,
+        depth_first=false,
+    ))]
     /// Create a `BitArrayRepresentation`, which can efficiently find possible surface configurations.
     ///  - `max_singlets`: the maximum amount of singlets the surface is allowed to have.
-    ///  - `use_filter`: whether to use the Similatiry filter. 
+    ///  - `use_filter`: whether to use the Similatiry filter.
     ///  - `difference_distance`: the minimum amount of distance needed to differentiate two structures
     ///     under the Similatiry filter.
     ///  - `max`: The size of the lattice. Should probably be kept as `None`.
+    ///  - `depth_first`: whether `solve` searches depth-first, which uses far less
+    ///     memory on large lattices.
     fn get_intermediary(
         &self,
         max_singlets: usize,
         difference_distance: f32,
         max: Option<(f32, f32)>,
         use_filter: bool,
+        depth_first: bool,
     ) -> BitArrayRepresentation {
+        let settings = BitArraySettings::create(
+            max_singlets,
+            difference_distance,
+            max.unwrap_or(self.wrapped.find_max()),
+            if use_filter {
+                BitArrayFilter::Flipped
+            } else {
+                BitArrayFilter::None
+            },
+        )
+        .with_strategy(if depth_first {
+            SolveStrategy::DepthFirst
+        } else {
+            SolveStrategy::BreadthFirst
+        });
         BitArrayRepresentation {
-            wrapped: self.wrapped.get_intermediary(BitArraySettings::create(
-                max_singlets,
-                difference_distance,
-                max.unwrap_or(self.wrapped.find_max()),
-                if use_filter {
-                    BitArrayFilter::Flipped
-                } else {
-                    BitArrayFilter::None
-                },
-            )),
+            wrapped: Arc::new(self.wrapped.get_intermediary(settings)),
         }
     }
 
-    /// Create a `SiteFilter` which can remove invalid silicon sites from a 
+    /// Create a `SiteFilter` which can remove invalid silicon sites from a
     /// `BitArrayRepresentation`. This filter removes silicons which have more
-    /// than one connection to a silicon of the previous layer, forming a small 
+    /// than one connection to a silicon of the previous layer, forming a small
     /// loop. These connections are very rare in real materials, and can therefore
     /// be excluded.
-    fn no_rings(&self) -> SiteFilter {
-        SiteFilter {
-            wrapped: self.wrapped.no_rings(),
-        }
+    fn no_rings(&self) -> PyResult<SiteFilter> {
+        Ok(SiteFilter {
+            wrapped: self.wrapped.no_rings().map_err(lattice_error)?,
+        })
     }
 
     /// Diagnostic information regarding the `no_rings` filter.
-    fn no_rings_plot(&self) -> Vec<(usize, f32, f32)> {
-        self.wrapped.no_rings_plot()
+    fn no_rings_plot(&self) -> PyResult<Vec<(usize, f32, f32)>> {
+        self.wrapped.no_rings_plot().map_err(lattice_error)
     }
 
     /// Turn a `BitArraySolution` back into a lattice, which can be exported and
@@ -198,20 +666,104 @@ impl Lattice {
         }
     }
 
+    /// A hash of the attachment points and the basis of the lattice, which is
+    /// the same on every machine and in every run.
+    fn fingerprint(&self) -> u64 {
+        self.wrapped.fingerprint()
+    }
+
+    /// A stable identifier of a `BitArraySolution` of this lattice, as 16
+    /// hexadecimal digits. It stays the same across reruns and machines, so
+    /// it can be used to name files and to compare results.
+    fn solution_id(&self, solution: &BitArraySolution) -> String {
+        self.wrapped.solution_id(&solution.wrapped)
+    }
+
     /// Export the found solution in a propriatary json format.
-    pub fn export(&self, path: OsString, name: String) {
-        self.wrapped.export(&path, name);
+    pub fn export(&self, path: OsString, name: String) -> PyResult<()> {
+        self.wrapped.export(&path, name).map_err(lattice_error)
+    }
+
+    /// Place the candidate sites and their crowns according to the site
+    /// geometry in the given json file. Keys missing from the file keep their
+    /// silicon dioxide defaults.
+    fn set_geometry(&mut self, filename: String) -> PyResult<()> {
+        let geometry = SiteGeometry::load(&filename).map_err(lattice_error)?;
+        self.wrapped.set_geometry(geometry);
+        Ok(())
+    }
+
+    #[pyo3(signature = (
+        midpoint_distance=None,
+        tripoint_distance=None,
+        tripoint_angle=None,
+        max_strain=None,
+        min_site_distance=0.0,
+        min_crown_distance=0.0,
+    ))]
+    /// Remove the midpoints and tripoints which do not satisfy the given rules.
+    ///  - `midpoint_distance`: (min, max) distance between the points of a midpoint.
+    ///  - `tripoint_distance`: (min, max) distance between the points of a tripoint.
+    ///  - `tripoint_angle`: (min, max) inner angles of a tripoint, in degrees.
+    ///  - `max_strain`: the largest relative difference between an in-plane side
+    ///    of a tripoint and its mean side.
+    ///  - `min_site_distance`: sites closer than this distance exclude each other.
+    ///  - `min_crown_distance`: sites with atoms, including their crowns, closer
+    ///    than this distance exclude each other.
+    ///
+    /// Rules which are not given accept every site.
+    #[allow(clippy::too_many_arguments)]
+    fn set_criteria(
+        &mut self,
+        midpoint_distance: Option<(f32, f32)>,
+        tripoint_distance: Option<(f32, f32)>,
+        tripoint_angle: Option<(f32, f32)>,
+        max_strain: Option<f32>,
+        min_site_distance: f32,
+        min_crown_distance: f32,
+    ) {
+        let window = |window: Option<(f32, f32)>| {
+            window.map_or(Window::ANY, |(min, max)| Window::new(min, max))
+        };
+        self.wrapped.set_criteria(SiteCriteria {
+            midpoint_distance: window(midpoint_distance),
+            tripoint_distance: window(tripoint_distance),
+            tripoint_angle: window(tripoint_angle),
+            max_strain: max_strain.unwrap_or(f32::INFINITY),
+            min_site_distance,
+            min_crown_distance,
+        });
     }
 
-    fn diagnostic_ase(&self) {
-        self.wrapped.diagnostic_ase();
+    /// Returns the groups of points which were rejected by `set_criteria`,
+    /// with the reason.
+    fn rejected_sites(&self) -> Vec<RejectedSite> {
+        self.wrapped
+            .rejected_sites()
+            .iter()
+            .map(|site| RejectedSite {
+                kind: site.kind.name().to_string(),
+                points: site.points.iter().map(|p| p.0).collect(),
+                reason: site.reason.to_string(),
+            })
+            .collect()
+    }
+
+    /// Save the complete lattice to a json file, which can be read with
+    /// `load_lattice`.
+    fn save(&self, filename: String) -> PyResult<()> {
+        self.wrapped.save(&filename).map_err(lattice_error)
+    }
+
+    fn diagnostic_ase(&self) -> PyResult<()> {
+        self.wrapped.diagnostic_ase().map_err(lattice_error)
     }
 
     /// Export the found surface configuration as an extention to the ASE json
     /// file used to construct the lattice. Does not work when lattice was not
     /// made from a file.
     #[pyo3(signature = (filename, folder=None))]
-    fn export_as_ase_json(&self, filename: String, folder: Option<String>) {
+    fn export_as_ase_json(&self, filename: String, folder: Option<String>) -> PyResult<()> {
         if let Some(folder_path) = folder {
             let mut full_path = folder_path.clone();
             full_path.push('/');
@@ -220,16 +772,95 @@ impl Lattice {
         } else {
             self.wrapped.export_as_ase_json(&filename)
         }
+        .map_err(lattice_error)
     }
 }
 
 #[pyfunction]
-#[pyo3(signature = (filename, distance_margin, autodetect_margin=true))]
+#[pyo3(signature = (
+    filename,
+    distance_margin,
+    autodetect_margin=true,
+    marker=1,
+    z_min=None,
+    z_max=Some(20.0),
+    top=None,
+    bottom=None,
+    indices=None,
+    tag=None,
+))]
+#[allow(clippy::too_many_arguments)]
 /// Put a new layer on top of an existing structure, calculated with DFT.
-fn from_dft_json(filename: String, distance_margin: f32, autodetect_margin: bool) -> Lattice {
-    Lattice {
-        wrapped: WrappedLattice::from_dft_json(filename, distance_margin, autodetect_margin),
-    }
+///  - marker: atomic number of the atoms marking the attachment sites.
+///  - z_min, z_max: only use markers with z_min <= z < z_max.
+///  - top, bottom: only use markers within this distance of the top or bottom
+///    of the slab. Replaces z_min and z_max.
+///  - indices: only use markers with these atom indices.
+///  - tag: only use markers with this ASE tag.
+fn from_dft_json(
+    filename: String,
+    distance_margin: f32,
+    autodetect_margin: bool,
+    marker: i64,
+    z_min: Option<f32>,
+    z_max: Option<f32>,
+    top: Option<f32>,
+    bottom: Option<f32>,
+    indices: Option<Vec<usize>>,
+    tag: Option<i64>,
+) -> PyResult<Lattice> {
+    let region = match (top, bottom) {
+        (Some(_), Some(_)) => {
+            return Err(PyValueError::new_err(
+                "top and bottom can not be used together",
+            ))
+        }
+        (Some(depth), None) => MarkerRegion::Top(depth),
+        (None, Some(depth)) => MarkerRegion::Bottom(depth),
+        (None, None) => MarkerRegion::Window(
+            z_min.unwrap_or(f32::NEG_INFINITY),
+            z_max.unwrap_or(f32::INFINITY),
+        ),
+    };
+    let mut attachment = AttachmentSpec::default().element(marker).region(region);
+    attachment.indices = indices;
+    attachment.tag = tag;
+
+    Ok(Lattice {
+        wrapped: WrappedLattice::from_dft_json_with(
+            filename,
+            distance_margin,
+            autodetect_margin,
+            attachment,
+        )
+        .map_err(lattice_error)?,
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (size_x, size_y, a=SIC_A, b=SIC_B))]
+/// Create the lattice of a flat supercell of `size_x` by `size_y` unit cells,
+/// spanned by the basis vectors `a` and `b`. Defaults to a SiC substrate.
+fn from_basis_vectors(size_x: usize, size_y: usize, a: [f32; 3], b: [f32; 3]) -> PyResult<Lattice> {
+    Ok(Lattice {
+        wrapped: WrappedLattice::from_basis_vectors(size_x, size_y, a, b).map_err(lattice_error)?,
+    })
+}
+
+#[pyfunction]
+/// Load a lattice saved with `Lattice.save`.
+fn load_lattice(filename: String) -> PyResult<Lattice> {
+    Ok(Lattice {
+        wrapped: WrappedLattice::load(&filename).map_err(lattice_error)?,
+    })
+}
+
+#[pyfunction]
+/// Load a representation saved with `BitArrayRepresentation.save`.
+fn load_intermediary(filename: String) -> PyResult<BitArrayRepresentation> {
+    Ok(BitArrayRepresentation {
+        wrapped: Arc::new(WrappedRepresentation::load(&filename).map_err(lattice_error)?),
+    })
 }
 
 #[pyfunction]
@@ -244,8 +875,17 @@ fn test_module() {
 fn lattice_solver(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(test_module, m)?)?;
     m.add_function(wrap_pyfunction!(from_dft_json, m)?)?;
+    m.add_function(wrap_pyfunction!(from_basis_vectors, m)?)?;
+    m.add_function(wrap_pyfunction!(load_lattice, m)?)?;
+    m.add_function(wrap_pyfunction!(load_intermediary, m)?)?;
     m.add_class::<Lattice>()?;
     m.add_class::<BitArrayRepresentation>()?;
     m.add_class::<BitArraySolution>()?;
+    m.add_class::<SolutionIterator>()?;
+    m.add_class::<AttachmentPoint>()?;
+    m.add_class::<CandidateSite>()?;
+    m.add_class::<RejectedSite>()?;
+    m.add_class::<SolveReport>()?;
+    m.add_class::<DepthStatistics>()?;
     Ok(())
 }